@@ -0,0 +1,58 @@
+extern crate Physics2D;
+
+use Physics2D::physics::Vec2D;
+use Physics2D::physics::Real;
+use Physics2D::physics::shapes::Chain;
+use Physics2D::physics::shapes::Polygon;
+use Physics2D::physics::fluid::Fluid;
+use Physics2D::physics::World;
+
+use std::env;
+use std::time::Instant;
+
+//Runs a dam break with a floating box in the arena from main.rs without opening a window
+//Usage: fluid_headless [steps] [particle spacing]
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let steps: usize = args.get(1).and_then(|arg| arg.parse().ok()).unwrap_or(500);
+    let spacing: Real = args.get(2).and_then(|arg| arg.parse().ok()).unwrap_or(0.5);
+
+    let mut world = World::new(0.0, 0.02);
+
+    let walls = Chain::new_loop(vec![
+        Vec2D::new(-15.0, 15.0),
+        Vec2D::new(15.0, 15.0),
+        Vec2D::new(15.0, -15.0),
+        Vec2D::new(-15.0, -15.0)
+    ]);
+    world.add_object(walls);
+
+    let floating_box = Polygon::new(vec![
+        Vec2D::new(5.0, 0.0),
+        Vec2D::new(8.0, 0.0),
+        Vec2D::new(8.0, 2.0),
+        Vec2D::new(5.0, 2.0)
+    ], 3.0).unwrap();
+    world.add_object(floating_box);
+
+    let mut water = Fluid::create_block(Vec2D::new(-15.0, -15.0), Vec2D::new(0.0, 0.0), spacing, 1.0);
+    water.gravity = Vec2D::new(0.0, -9.8);
+    world.add_fluid(water);
+
+    println!("Particles: {}", world.fluids[0].len());
+
+    let start = Instant::now();
+    for _ in 0..steps {
+        //World has no gravity for rigid bodies so the box is pulled down by hand
+        let velocity = world.objects[1].get_velocity();
+        world.objects[1].set_velocity(&velocity.add(&Vec2D::new(0.0, -9.8 * world.timestep)));
+        world.update();
+    }
+    let elapsed = Instant::now().duration_since(start);
+    let elapsed_sec = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1000000000.0;
+
+    println!("Steps: {}", steps);
+    println!("Total time: {:.3} s", elapsed_sec);
+    println!("Time per step: {:.3} ms", elapsed_sec * 1000.0 / steps as f64);
+    println!("Checksum: {:016x}", world.checksum());
+}
@@ -1,4 +1,12 @@
 extern crate piston_window;
+extern crate rand;
+#[cfg(feature = "serialize")]
+extern crate serde;
+#[cfg(feature = "serialize")]
+#[macro_use]
+extern crate serde_derive;
+#[cfg(feature = "serialize")]
+extern crate serde_json;
 
 pub mod physics;
 pub mod renderer;
\ No newline at end of file
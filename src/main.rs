@@ -8,11 +8,13 @@ extern crate rand;
 use rand::Rng;
 
 use Physics2D::physics::Vec2D;
+use Physics2D::physics::Real;
 use Physics2D::physics::shapes::Circle;
-use Physics2D::physics::shapes::Line;
+use Physics2D::physics::shapes::Chain;
 use Physics2D::physics::shapes::Group;
 use Physics2D::physics::Object;
 use Physics2D::physics::World;
+use Physics2D::physics::bounds::Aabb;
 use Physics2D::renderer::Renderable;
 use Physics2D::renderer::Camera;
 
@@ -22,10 +24,12 @@ fn main() {
     let mut window: PistonWindow = WindowSettings::new("Hello World", [800,800]).exit_on_esc(true).build().unwrap();
     let mut world = World::new(0.0, 1.0);
 
-    let mut wall1 = Line::new(Vec2D::new(-15.0, 15.0), Vec2D::new(15.0, 15.0));
-    let mut wall2 = Line::new(Vec2D::new(15.0, 15.0), Vec2D::new(15.0, -15.0));
-    let mut wall3 = Line::new(Vec2D::new(15.0, -15.0), Vec2D::new(-15.0, -15.0));
-    let mut wall4 = Line::new(Vec2D::new(-15.0, -15.0), Vec2D::new(-15.0, 15.0));
+    let walls = Chain::new_loop(vec![
+        Vec2D::new(-15.0, 15.0),
+        Vec2D::new(15.0, 15.0),
+        Vec2D::new(15.0, -15.0),
+        Vec2D::new(-15.0, -15.0)
+    ]);
 
     let ball1 = Circle::new(3.0, Vec2D::new(5.0, 5.0), 0.4);
     let ball2 = Circle::new(3.0, Vec2D::new(9.0, 5.0), 0.4);
@@ -39,20 +43,20 @@ fn main() {
     for i in 0..10 {
         let mut rng = rand::thread_rng();
 
-        let rand_x = rng.gen_range::<f64>(-10.0, 10.0);
-        let rand_y = rng.gen_range::<f64>(-10.0, 10.0);
-        let rand_rot = rng.gen_range::<f64>(0.0, 6.2831852);
-        let rand_mass = rng.gen_range::<f64>(1.0, 7.0);
-        let r = rng.gen_range::<f64>(1.0, 1.5);
+        let rand_x = rng.gen_range::<Real>(-10.0, 10.0);
+        let rand_y = rng.gen_range::<Real>(-10.0, 10.0);
+        let rand_rot = rng.gen_range::<Real>(0.0, 6.2831852);
+        let rand_mass = rng.gen_range::<Real>(1.0, 7.0);
+        let r = rng.gen_range::<Real>(1.0, 1.5);
         let n = rng.gen_range::<i32>(4, 8);
 
-        let rand_vx = rng.gen_range::<f64>(-0.1, 0.1);
-        let rand_vy = rng.gen_range::<f64>(-0.1, 0.1);
+        let rand_vx = rng.gen_range::<Real>(-0.1, 0.1);
+        let rand_vy = rng.gen_range::<Real>(-0.1, 0.1);
 
         let mut points_polygon: Vec<Vec2D> = Vec::new();
         for i in 0..(n+1) {
-            let x: f64 = rand_x + r * (rand_rot + i as f64 * 6.2831852 / n as f64).cos();
-            let y: f64 = rand_y +r * (rand_rot + i as f64* 6.2831852 / n as f64).sin();
+            let x: Real = rand_x + r * (rand_rot + i as Real * 6.2831852 / n as Real).cos();
+            let y: Real = rand_y +r * (rand_rot + i as Real* 6.2831852 / n as Real).sin();
             points_polygon.push(Vec2D::new(x, y));
         }
         let mut polygon = Group::create_polygon(points_polygon, rand_mass);
@@ -61,16 +65,16 @@ fn main() {
     }
 
     world.add_object(group1);
-    world.add_object(wall1);
-    world.add_object(wall2);
-    world.add_object(wall3);
-    world.add_object(wall4);
+    world.add_object(walls);
+
+    //Bodies that tunnel through the walls are removed once they leave the view
+    world.bounds = Some(Aabb::new(Vec2D::new(-20.0, -20.0), Vec2D::new(20.0, 20.0)));
 
     while let Some(e) = window.next() {
         let prev_time = Instant::now();
         window.draw_2d(&e, |c, g| {
             clear([1.0,1.0,1.0,1.0], g);
-            let camera: Camera = Camera::new(-20.0, 20.0, -20.0, 20.0, c.get_view_size()[0], c.get_view_size()[1]);
+            let camera: Camera = Camera::new(-20.0, 20.0, -20.0, 20.0, c.get_view_size()[0] as Real, c.get_view_size()[1] as Real);
             world.render(&c, g, &camera);
             world.update();
         });
@@ -0,0 +1,1029 @@
+use physics::Vec2D;
+use physics::Real;
+use physics::World;
+use physics::bounds::Aabb;
+use physics::bounds::OutOfBounds;
+use physics::shapes::*;
+
+use renderer::RenderableObject;
+
+use std::fmt;
+use std::error::Error;
+use std::convert::TryFrom;
+
+//Compact binary encoding of the world settings and rigid bodies for recording and networking
+//Like physics::scene the other subsystems (soft bodies, fluids...) are not included
+//
+//Layout of a state (integers are LEB128 varints, signed ones zigzag encoded, reals are little endian f64):
+//  "P2DS" version flags [position_step velocity_step] gravity timestep periodic bounds body_count bodies...
+//Every body is tag length payload (shared properties then the shape) so readers can skip bodies without decoding them
+
+const STATE_MAGIC: &[u8] = b"P2DS";
+const DELTA_MAGIC: &[u8] = b"P2DD";
+//Bumped whenever the layout changes... older readers reject newer data
+pub const FORMAT_VERSION: u8 = 1;
+
+const FLAG_QUANTIZED: u8 = 1;
+
+const TAG_CIRCLE: u8 = 0;
+const TAG_LINE: u8 = 1;
+const TAG_POLYGON: u8 = 2;
+const TAG_CHAIN: u8 = 3;
+const TAG_HEIGHTFIELD: u8 = 4;
+const TAG_GROUP: u8 = 5;
+
+//Groups nested deeper than this are rejected so corrupt data can't overflow the stack
+const MAX_GROUP_DEPTH: usize = 64;
+//A delta may rebuild at most this many bytes per delta byte past the end of the base
+//encode_delta stays far below this since unchanged runs past the base are only zero bytes
+const MAX_DELTA_EXPANSION: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryError {
+    BadMagic,
+    UnsupportedVersion(u8),
+    UnexpectedEnd,
+    UnknownBody(u8),
+    //Body at this index of World::objects isn't one of the shapes in physics::shapes
+    UnsupportedBody(usize),
+    //Delta was made against different base data
+    BaseMismatch,
+    //Quantization steps must be positive and finite
+    InvalidQuantization,
+    Malformed
+}
+
+impl fmt::Display for BinaryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BinaryError::BadMagic => write!(f, "data is not a binary world state"),
+            BinaryError::UnsupportedVersion(version) => write!(f, "format version {} is newer than the supported version {}", version, FORMAT_VERSION),
+            BinaryError::UnexpectedEnd => write!(f, "data ended early"),
+            BinaryError::UnknownBody(tag) => write!(f, "unknown body tag {}", tag),
+            BinaryError::UnsupportedBody(index) => write!(f, "body {} can't be encoded", index),
+            BinaryError::BaseMismatch => write!(f, "delta doesn't belong to the given base"),
+            BinaryError::InvalidQuantization => write!(f, "quantization steps must be positive and finite"),
+            BinaryError::Malformed => write!(f, "malformed data")
+        }
+    }
+}
+
+impl Error for BinaryError {}
+
+//Positions and velocities are rounded to multiples of these steps and stored as small integers
+//Everything else (masses, radii, colors...) stays exact
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quantization {
+    pub position_step: Real,
+    pub velocity_step: Real
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EncodeOptions {
+    //Exact when None
+    pub quantization: Option<Quantization>
+}
+
+impl EncodeOptions {
+    pub fn exact() -> EncodeOptions {
+        EncodeOptions {quantization: None}
+    }
+
+    pub fn quantized(position_step: Real, velocity_step: Real) -> EncodeOptions {
+        EncodeOptions {quantization: Some(Quantization {position_step, velocity_step})}
+    }
+}
+
+struct Writer {
+    bytes: Vec<u8>,
+    quantization: Option<Quantization>
+}
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.bytes.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.bytes.push(value as u8);
+    }
+
+    fn zigzag(&mut self, value: i64) {
+        self.varint(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    fn real(&mut self, value: Real) {
+        let bits = (value as f64).to_bits();
+        for i in 0..8 {
+            self.bytes.push((bits >> (i * 8)) as u8);
+        }
+    }
+
+    fn f32(&mut self, value: f32) {
+        let bits = value.to_bits();
+        for i in 0..4 {
+            self.bytes.push((bits >> (i * 8)) as u8);
+        }
+    }
+
+    fn vec(&mut self, value: &Vec2D) {
+        self.real(value.x);
+        self.real(value.y);
+    }
+
+    fn quantized(&mut self, value: Real, step: Real) {
+        self.zigzag((value / step).round() as i64);
+    }
+
+    fn coordinate(&mut self, value: Real) {
+        match self.quantization {
+            Some(quantization) => self.quantized(value, quantization.position_step),
+            None => self.real(value)
+        }
+    }
+
+    fn position(&mut self, value: &Vec2D) {
+        self.coordinate(value.x);
+        self.coordinate(value.y);
+    }
+
+    fn velocity(&mut self, value: &Vec2D) {
+        match self.quantization {
+            Some(quantization) => {
+                self.quantized(value.x, quantization.velocity_step);
+                self.quantized(value.y, quantization.velocity_step);
+            },
+            None => self.vec(value)
+        }
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.u8(if value { 1 } else { 0 });
+    }
+
+    fn color(&mut self, color: &[f32; 4]) {
+        for &channel in color.iter() {
+            self.f32(channel);
+        }
+    }
+
+    fn aabb(&mut self, bounds: &Option<Aabb>) {
+        match *bounds {
+            Some(bounds) => {
+                self.u8(1);
+                self.vec(&bounds.min);
+                self.vec(&bounds.max);
+            },
+            None => self.u8(0)
+        }
+    }
+
+    //Properties shared by every shape
+    fn properties(&mut self, body: &RenderableObject) {
+        self.real(body.get_mass());
+        self.velocity(&body.get_velocity());
+        self.real(body.get_friction());
+        self.bool(body.get_static());
+        self.varint(body.get_category() as u64);
+        self.real(body.get_charge());
+        self.u8(match body.get_out_of_bounds() {
+            OutOfBounds::Despawn => 0,
+            OutOfBounds::Clamp => 1,
+            OutOfBounds::Event => 2
+        });
+    }
+
+    fn points(&mut self, points: &[Vec2D]) {
+        self.varint(points.len() as u64);
+        for point in points.iter() {
+            self.position(point);
+        }
+    }
+
+    fn body(&mut self, body: &RenderableObject) -> Result<(), ()> {
+        let any = body.as_any();
+        let mut payload = Writer {bytes: Vec::new(), quantization: self.quantization};
+        payload.properties(body);
+        let tag = if any.is::<Circle>() {
+            let circle = any.downcast_ref::<Circle>().unwrap();
+            payload.position(&circle.center);
+            payload.real(circle.radius);
+            payload.color(&circle.color);
+            TAG_CIRCLE
+        } else if any.is::<Line>() {
+            let line = any.downcast_ref::<Line>().unwrap();
+            payload.position(&line.start_point);
+            payload.position(&line.end_point);
+            payload.color(&line.color);
+            TAG_LINE
+        } else if any.is::<Polygon>() {
+            let polygon = any.downcast_ref::<Polygon>().unwrap();
+            payload.points(&polygon.points);
+            payload.color(&polygon.color);
+            TAG_POLYGON
+        } else if any.is::<Chain>() {
+            let chain = any.downcast_ref::<Chain>().unwrap();
+            payload.points(&chain.points);
+            payload.bool(chain.is_loop);
+            for ghost in [chain.ghost_start, chain.ghost_end].iter() {
+                match *ghost {
+                    Some(ref point) => {
+                        payload.u8(1);
+                        payload.position(point);
+                    },
+                    None => payload.u8(0)
+                }
+            }
+            payload.color(&chain.color);
+            TAG_CHAIN
+        } else if any.is::<Heightfield>() {
+            let heightfield = any.downcast_ref::<Heightfield>().unwrap();
+            payload.position(&heightfield.origin);
+            payload.real(heightfield.spacing);
+            payload.real(heightfield.depth);
+            payload.varint(heightfield.heights.len() as u64);
+            for &height in heightfield.heights.iter() {
+                payload.coordinate(height);
+            }
+            payload.color(&heightfield.color);
+            TAG_HEIGHTFIELD
+        } else if any.is::<Group>() {
+            let group = any.downcast_ref::<Group>().unwrap();
+            payload.position(&group.com);
+            payload.varint(group.objects.len() as u64);
+            for member in group.objects.iter() {
+                payload.body(&**member)?;
+            }
+            TAG_GROUP
+        } else {
+            return Err(());
+        };
+
+        self.u8(tag);
+        self.varint(payload.bytes.len() as u64);
+        self.bytes.extend_from_slice(&payload.bytes);
+        Ok(())
+    }
+}
+
+//Properties shared by every shape in the order they are written
+struct Properties {
+    mass: Real,
+    velocity: Vec2D,
+    friction: Real,
+    is_static: bool,
+    category: u32,
+    charge: Real,
+    out_of_bounds: OutOfBounds
+}
+
+impl Properties {
+    //Group::set_mass is ignored so groups set their mass directly
+    fn apply(&self, body: &mut RenderableObject) {
+        body.set_mass(self.mass);
+        body.set_velocity(&self.velocity);
+        body.set_friction(self.friction);
+        body.set_static(self.is_static);
+        body.set_category(self.category);
+        body.set_charge(self.charge);
+        body.set_out_of_bounds(self.out_of_bounds);
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    quantization: Option<Quantization>
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8], quantization: Option<Quantization>) -> Reader<'a> {
+        Reader {bytes, position: 0, quantization}
+    }
+
+    fn is_empty(&self) -> bool {
+        self.position >= self.bytes.len()
+    }
+
+    fn slice(&mut self, length: usize) -> Result<&'a [u8], BinaryError> {
+        if self.bytes.len() - self.position < length {
+            return Err(BinaryError::UnexpectedEnd);
+        }
+
+        let slice = &self.bytes[self.position..self.position + length];
+        self.position += length;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, BinaryError> {
+        Ok(self.slice(1)?[0])
+    }
+
+    fn varint(&mut self) -> Result<u64, BinaryError> {
+        let mut value = 0u64;
+        for shift in 0..10 {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7f) as u64) << (shift * 7);
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(BinaryError::Malformed)
+    }
+
+    //Lengths are checked against the remaining data so corrupt input can't request huge allocations
+    fn length(&mut self) -> Result<usize, BinaryError> {
+        let length = self.varint()?;
+        if length > (self.bytes.len() - self.position) as u64 {
+            return Err(BinaryError::UnexpectedEnd);
+        }
+
+        Ok(length as usize)
+    }
+
+    fn zigzag(&mut self) -> Result<i64, BinaryError> {
+        let value = self.varint()?;
+        Ok(((value >> 1) as i64) ^ -((value & 1) as i64))
+    }
+
+    fn real(&mut self) -> Result<Real, BinaryError> {
+        let bytes = self.slice(8)?;
+        let mut bits = 0u64;
+        for i in 0..8 {
+            bits |= (bytes[i] as u64) << (i * 8);
+        }
+
+        Ok(f64::from_bits(bits) as Real)
+    }
+
+    fn f32(&mut self) -> Result<f32, BinaryError> {
+        let bytes = self.slice(4)?;
+        let mut bits = 0u32;
+        for i in 0..4 {
+            bits |= (bytes[i] as u32) << (i * 8);
+        }
+
+        Ok(f32::from_bits(bits))
+    }
+
+    fn vec(&mut self) -> Result<Vec2D, BinaryError> {
+        Ok(Vec2D::new(self.real()?, self.real()?))
+    }
+
+    fn coordinate(&mut self) -> Result<Real, BinaryError> {
+        match self.quantization {
+            Some(quantization) => Ok(self.zigzag()? as Real * quantization.position_step),
+            None => self.real()
+        }
+    }
+
+    fn position(&mut self) -> Result<Vec2D, BinaryError> {
+        Ok(Vec2D::new(self.coordinate()?, self.coordinate()?))
+    }
+
+    fn velocity(&mut self) -> Result<Vec2D, BinaryError> {
+        match self.quantization {
+            Some(quantization) => Ok(Vec2D::new(
+                self.zigzag()? as Real * quantization.velocity_step,
+                self.zigzag()? as Real * quantization.velocity_step
+            )),
+            None => self.vec()
+        }
+    }
+
+    fn bool(&mut self) -> Result<bool, BinaryError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(BinaryError::Malformed)
+        }
+    }
+
+    fn color(&mut self) -> Result<[f32; 4], BinaryError> {
+        Ok([self.f32()?, self.f32()?, self.f32()?, self.f32()?])
+    }
+
+    fn aabb(&mut self) -> Result<Option<Aabb>, BinaryError> {
+        match self.u8()? {
+            0 => Ok(None),
+            1 => Ok(Some(Aabb::new(self.vec()?, self.vec()?))),
+            _ => Err(BinaryError::Malformed)
+        }
+    }
+
+    fn properties(&mut self) -> Result<Properties, BinaryError> {
+        let mass = self.real()?;
+        let velocity = self.velocity()?;
+        let friction = self.real()?;
+        let is_static = self.bool()?;
+        let category = self.varint()?;
+        if category > u32::max_value() as u64 {
+            return Err(BinaryError::Malformed);
+        }
+        let charge = self.real()?;
+        let out_of_bounds = match self.u8()? {
+            0 => OutOfBounds::Despawn,
+            1 => OutOfBounds::Clamp,
+            2 => OutOfBounds::Event,
+            _ => return Err(BinaryError::Malformed)
+        };
+
+        Ok(Properties {mass, velocity, friction, is_static, category: category as u32, charge, out_of_bounds})
+    }
+
+    fn points(&mut self) -> Result<Vec<Vec2D>, BinaryError> {
+        let count = self.length()?;
+        let mut points = Vec::with_capacity(count);
+        for _ in 0..count {
+            points.push(self.position()?);
+        }
+
+        Ok(points)
+    }
+
+    fn body_view(&mut self) -> Result<BodyView<'a>, BinaryError> {
+        let tag = self.u8()?;
+        let length = self.length()?;
+        Ok(BodyView {tag, payload: self.slice(length)?, quantization: self.quantization})
+    }
+}
+
+//Encoded body borrowed from the state data
+#[derive(Debug, Clone, Copy)]
+pub struct BodyView<'a> {
+    tag: u8,
+    payload: &'a [u8],
+    quantization: Option<Quantization>
+}
+
+impl<'a> BodyView<'a> {
+    pub fn is_circle(&self) -> bool { self.tag == TAG_CIRCLE }
+    pub fn is_line(&self) -> bool { self.tag == TAG_LINE }
+    pub fn is_polygon(&self) -> bool { self.tag == TAG_POLYGON }
+    pub fn is_chain(&self) -> bool { self.tag == TAG_CHAIN }
+    pub fn is_heightfield(&self) -> bool { self.tag == TAG_HEIGHTFIELD }
+    pub fn is_group(&self) -> bool { self.tag == TAG_GROUP }
+
+    //Encoded bytes of the body
+    pub fn payload(&self) -> &'a [u8] {
+        self.payload
+    }
+
+    pub fn decode(&self) -> Result<Box<RenderableObject>, BinaryError> {
+        self.decode_nested(0)
+    }
+
+    //depth is the number of groups this body is inside of
+    fn decode_nested(&self, depth: usize) -> Result<Box<RenderableObject>, BinaryError> {
+        let mut reader = Reader::new(self.payload, self.quantization);
+        let properties = reader.properties()?;
+        let mut body: Box<RenderableObject> = match self.tag {
+            TAG_CIRCLE => {
+                let center = reader.position()?;
+                let radius = reader.real()?;
+                let mut circle = Circle::new(0.0, center, radius);
+                circle.color = reader.color()?;
+                Box::new(circle)
+            },
+            TAG_LINE => {
+                let start_point = reader.position()?;
+                let end_point = reader.position()?;
+                let mut line = Line::new(start_point, end_point);
+                line.color = reader.color()?;
+                Box::new(line)
+            },
+            TAG_POLYGON => {
+                //Points are taken as stored since Polygon::new could drop points that quantization made collinear
+                let points = reader.points()?;
+                if points.len() < 3 {
+                    return Err(BinaryError::Malformed);
+                }
+                Box::new(Polygon {
+                    points,
+                    mass: 0.0,
+                    velocity: Vec2D::new(0.0, 0.0),
+                    color: reader.color()?,
+                    friction: 0.0,
+                    is_static: false,
+                    category: 1,
+                    charge: 0.0,
+                    out_of_bounds: OutOfBounds::Despawn
+                })
+            },
+            TAG_CHAIN => {
+                let mut chain = Chain::new(reader.points()?);
+                chain.is_loop = reader.bool()?;
+                chain.ghost_start = if reader.bool()? { Some(reader.position()?) } else { None };
+                chain.ghost_end = if reader.bool()? { Some(reader.position()?) } else { None };
+                chain.color = reader.color()?;
+                chain.rebuild_bounds();
+                Box::new(chain)
+            },
+            TAG_HEIGHTFIELD => {
+                let origin = reader.position()?;
+                let spacing = reader.real()?;
+                let depth = reader.real()?;
+                let count = reader.length()?;
+                let mut heights = Vec::with_capacity(count);
+                for _ in 0..count {
+                    heights.push(reader.coordinate()?);
+                }
+                let mut heightfield = Heightfield::new(origin, spacing, heights).map_err(|_| BinaryError::Malformed)?;
+                heightfield.depth = depth;
+                heightfield.color = reader.color()?;
+                Box::new(heightfield)
+            },
+            TAG_GROUP => {
+                if depth >= MAX_GROUP_DEPTH {
+                    return Err(BinaryError::Malformed);
+                }
+                let mut group = Group::new();
+                group.com = reader.position()?;
+                let count = reader.length()?;
+                for _ in 0..count {
+                    group.objects.push(reader.body_view()?.decode_nested(depth + 1)?);
+                }
+                group.update_bounds();
+                group.mass = properties.mass;
+                Box::new(group)
+            },
+            tag => return Err(BinaryError::UnknownBody(tag))
+        };
+        properties.apply(&mut *body);
+
+        if !reader.is_empty() {
+            return Err(BinaryError::Malformed);
+        }
+
+        Ok(body)
+    }
+}
+
+//Header of an encoded state... bodies are read straight from the borrowed data when asked for
+pub struct StateView<'a> {
+    pub quantization: Option<Quantization>,
+    pub gravity: Real,
+    pub timestep: Real,
+    pub periodic: Option<Aabb>,
+    pub bounds: Option<Aabb>,
+    pub body_count: usize,
+    bodies: &'a [u8]
+}
+
+impl<'a> StateView<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<StateView<'a>, BinaryError> {
+        let mut reader = Reader::new(data, None);
+        if reader.slice(STATE_MAGIC.len()).map_err(|_| BinaryError::BadMagic)? != STATE_MAGIC {
+            return Err(BinaryError::BadMagic);
+        }
+        let version = reader.u8()?;
+        if version > FORMAT_VERSION {
+            return Err(BinaryError::UnsupportedVersion(version));
+        }
+
+        let flags = reader.u8()?;
+        let quantization = if flags & FLAG_QUANTIZED != 0 {
+            let position_step = reader.real()?;
+            let velocity_step = reader.real()?;
+            if !(position_step > 0.0 && position_step.is_finite() && velocity_step > 0.0 && velocity_step.is_finite()) {
+                return Err(BinaryError::InvalidQuantization);
+            }
+            Some(Quantization {position_step, velocity_step})
+        } else {
+            None
+        };
+
+        let gravity = reader.real()?;
+        let timestep = reader.real()?;
+        let periodic = reader.aabb()?;
+        let bounds = reader.aabb()?;
+        let body_count = reader.varint()? as usize;
+
+        Ok(StateView {
+            quantization,
+            gravity,
+            timestep,
+            periodic,
+            bounds,
+            body_count,
+            bodies: &data[reader.position..]
+        })
+    }
+
+    //Bodies in the order of World::objects
+    pub fn bodies(&self) -> Bodies<'a> {
+        Bodies {reader: Reader::new(self.bodies, self.quantization), remaining: self.body_count}
+    }
+
+    pub fn to_world(&self) -> Result<World, BinaryError> {
+        let mut world = World::new(self.gravity, self.timestep);
+        world.periodic = self.periodic;
+        world.bounds = self.bounds;
+        for body in self.bodies() {
+            world.objects.push(body?.decode()?);
+        }
+
+        Ok(world)
+    }
+}
+
+pub struct Bodies<'a> {
+    reader: Reader<'a>,
+    remaining: usize
+}
+
+impl<'a> Iterator for Bodies<'a> {
+    type Item = Result<BodyView<'a>, BinaryError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        self.remaining -= 1;
+        let body = self.reader.body_view();
+        if body.is_err() {
+            self.remaining = 0;
+        }
+
+        Some(body)
+    }
+}
+
+pub fn encode(world: &World, options: &EncodeOptions) -> Result<Vec<u8>, BinaryError> {
+    if let Some(quantization) = options.quantization {
+        for &step in [quantization.position_step, quantization.velocity_step].iter() {
+            if !(step > 0.0 && step.is_finite()) {
+                return Err(BinaryError::InvalidQuantization);
+            }
+        }
+    }
+
+    let mut writer = Writer {bytes: Vec::new(), quantization: None};
+    writer.bytes.extend_from_slice(STATE_MAGIC);
+    writer.u8(FORMAT_VERSION);
+    match options.quantization {
+        Some(quantization) => {
+            writer.u8(FLAG_QUANTIZED);
+            writer.real(quantization.position_step);
+            writer.real(quantization.velocity_step);
+        },
+        None => writer.u8(0)
+    }
+    writer.real(world.gravity);
+    writer.real(world.timestep);
+    writer.aabb(&world.periodic);
+    writer.aabb(&world.bounds);
+    writer.varint(world.objects.len() as u64);
+
+    writer.quantization = options.quantization;
+    for (index, body) in world.objects.iter().enumerate() {
+        writer.body(&**body).map_err(|_| BinaryError::UnsupportedBody(index))?;
+    }
+
+    Ok(writer.bytes)
+}
+
+pub fn decode(data: &[u8]) -> Result<World, BinaryError> {
+    StateView::parse(data)?.to_world()
+}
+
+//FNV-1a hash used to tie a delta to its base
+fn hash(data: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for &byte in data.iter() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    hash
+}
+
+//Difference between two encoded states (ex. consecutive recorded frames)
+//Bytes are XORed with the base and runs of unchanged bytes are stored as a count
+//Quantized states compress best since small movements only change the low bytes
+pub fn encode_delta(current: &[u8], base: &[u8]) -> Vec<u8> {
+    let mut writer = Writer {bytes: Vec::new(), quantization: None};
+    writer.bytes.extend_from_slice(DELTA_MAGIC);
+    writer.u8(FORMAT_VERSION);
+    let base_hash = hash(base);
+    for i in 0..8 {
+        writer.u8((base_hash >> (i * 8)) as u8);
+    }
+    writer.varint(current.len() as u64);
+
+    //Runs of (unchanged count, changed count, changed bytes XOR base)
+    let difference = |i: usize| current[i] ^ base.get(i).cloned().unwrap_or(0);
+    let mut i = 0;
+    while i < current.len() {
+        let start = i;
+        while i < current.len() && difference(i) == 0 {
+            i += 1;
+        }
+        writer.varint((i - start) as u64);
+
+        //Short unchanged gaps are kept inside the changed run since a new run costs two counts
+        let start = i;
+        while i < current.len() && (difference(i) != 0 || (i + 2 < current.len() && (difference(i + 1) != 0 || difference(i + 2) != 0))) {
+            i += 1;
+        }
+        writer.varint((i - start) as u64);
+        for j in start..i {
+            writer.u8(difference(j));
+        }
+    }
+
+    writer.bytes
+}
+
+//Rebuilds the encoded state a delta was made from... decode the result with StateView::parse or decode
+pub fn apply_delta(delta: &[u8], base: &[u8]) -> Result<Vec<u8>, BinaryError> {
+    let mut reader = Reader::new(delta, None);
+    if reader.slice(DELTA_MAGIC.len()).map_err(|_| BinaryError::BadMagic)? != DELTA_MAGIC {
+        return Err(BinaryError::BadMagic);
+    }
+    let version = reader.u8()?;
+    if version > FORMAT_VERSION {
+        return Err(BinaryError::UnsupportedVersion(version));
+    }
+    let hash_bytes = reader.slice(8)?;
+    let mut base_hash = 0u64;
+    for i in 0..8 {
+        base_hash |= (hash_bytes[i] as u64) << (i * 8);
+    }
+    if base_hash != hash(base) {
+        return Err(BinaryError::BaseMismatch);
+    }
+
+    //Bytes past the base can only come from the rest of the delta so huge lengths are corrupt
+    let length = usize::try_from(reader.varint()?).map_err(|_| BinaryError::Malformed)?;
+    let remaining = delta.len() - reader.position;
+    if length > base.len().saturating_add(remaining.saturating_mul(MAX_DELTA_EXPANSION)) {
+        return Err(BinaryError::Malformed);
+    }
+    let mut current = Vec::with_capacity(length);
+    while current.len() < length {
+        let unchanged = reader.varint()?;
+        if unchanged > (length - current.len()) as u64 {
+            return Err(BinaryError::Malformed);
+        }
+        for _ in 0..unchanged {
+            let i = current.len();
+            current.push(base.get(i).cloned().unwrap_or(0));
+        }
+        let changed = reader.length()?;
+        for &byte in reader.slice(changed)?.iter() {
+            let i = current.len();
+            current.push(byte ^ base.get(i).cloned().unwrap_or(0));
+        }
+        if unchanged == 0 && changed == 0 {
+            return Err(BinaryError::Malformed);
+        }
+    }
+    if current.len() != length || !reader.is_empty() {
+        return Err(BinaryError::Malformed);
+    }
+
+    Ok(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use physics::Object;
+
+    fn square(min: Vec2D, size: Real) -> Vec<Vec2D> {
+        vec![
+            min,
+            Vec2D::new(min.x + size, min.y),
+            Vec2D::new(min.x + size, min.y + size),
+            Vec2D::new(min.x, min.y + size)
+        ]
+    }
+
+    //One of every shape in physics::shapes with non-default properties
+    fn bodies() -> Vec<Box<RenderableObject>> {
+        let mut circle = Circle::new(2.0, Vec2D::new(-5.0, 0.25), 1.0);
+        circle.velocity = Vec2D::new(4.0, -1.0);
+        circle.charge = 0.5;
+        circle.color = [1.0, 0.5, 0.25, 1.0];
+        circle.out_of_bounds = OutOfBounds::Clamp;
+
+        let mut line = Line::new(Vec2D::new(-8.0, 5.0), Vec2D::new(-2.0, 6.0));
+        line.category = 4;
+        line.friction = 0.2;
+
+        let mut polygon = Polygon::new(square(Vec2D::new(5.0, 0.0), 2.0), 3.0).unwrap();
+        polygon.velocity = Vec2D::new(-1.0, 0.5);
+        polygon.out_of_bounds = OutOfBounds::Event;
+
+        let mut chain = Chain::new(vec![Vec2D::new(-15.0, -15.0), Vec2D::new(0.0, -14.0), Vec2D::new(15.0, -15.0)]);
+        chain.ghost_start = Some(Vec2D::new(-20.0, -15.0));
+
+        let mut heightfield = Heightfield::new(Vec2D::new(-10.0, -14.0), 0.5, vec![0.0, 1.0, 0.5, 0.25]).unwrap();
+        heightfield.depth = 5.0;
+
+        //Groups inside groups keep their own mass and members
+        let mut inner = Group::new();
+        inner.add_object(Circle::new(1.0, Vec2D::new(0.0, 0.0), 0.5));
+        inner.add_object(Group::create_polygon(vec![
+            Vec2D::new(2.0, 0.0),
+            Vec2D::new(3.0, 0.0),
+            Vec2D::new(3.0, 1.0),
+            Vec2D::new(2.0, 0.0)
+        ], 2.0));
+        let mut group = Group::new();
+        group.add_object(inner);
+        group.add_object(Line::new(Vec2D::new(3.0, 0.0), Vec2D::new(3.0, 1.0)));
+        group.set_velocity(&Vec2D::new(3.0, 2.0));
+
+        vec![Box::new(circle), Box::new(line), Box::new(polygon), Box::new(chain), Box::new(heightfield), Box::new(group)]
+    }
+
+    fn world() -> World {
+        let mut world = World::new(9.8, 0.02);
+        world.bounds = Some(Aabb::new(Vec2D::new(-20.0, -20.0), Vec2D::new(20.0, 20.0)));
+        world.objects = bodies();
+        world
+    }
+
+    #[test]
+    fn every_shape_round_trips_exactly() {
+        for body in bodies() {
+            let mut world = World::new(0.0, 0.01);
+            world.objects.push(body);
+
+            let data = encode(&world, &EncodeOptions::exact()).unwrap();
+            let decoded = decode(&data).unwrap();
+            assert_eq!(decoded.checksum(), world.checksum());
+            //Colors, charges and the other properties missing from the checksum must survive too
+            assert_eq!(encode(&decoded, &EncodeOptions::exact()).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn world_settings_round_trip() {
+        let mut world = world();
+        world.periodic = Some(Aabb::new(Vec2D::new(-10.0, -10.0), Vec2D::new(10.0, 10.0)));
+
+        let decoded = decode(&encode(&world, &EncodeOptions::exact()).unwrap()).unwrap();
+        assert_eq!(decoded.gravity, world.gravity);
+        assert_eq!(decoded.timestep, world.timestep);
+        assert_eq!(decoded.periodic, world.periodic);
+        assert_eq!(decoded.bounds, world.bounds);
+        assert_eq!(decoded.checksum(), world.checksum());
+    }
+
+    #[test]
+    fn nested_group_round_trips() {
+        let world = world();
+        let decoded = decode(&encode(&world, &EncodeOptions::exact()).unwrap()).unwrap();
+
+        let original = world.objects[5].as_any().downcast_ref::<Group>().unwrap();
+        let group = decoded.objects[5].as_any().downcast_ref::<Group>().unwrap();
+        assert_eq!(group.objects.len(), original.objects.len());
+        assert_eq!(group.mass, original.mass);
+        assert_eq!(group.com, original.com);
+
+        let inner = group.objects[0].as_any().downcast_ref::<Group>().unwrap();
+        assert_eq!(inner.objects.len(), 2);
+        assert!(inner.objects[0].as_any().is::<Circle>());
+        assert!(inner.objects[1].as_any().is::<Group>());
+    }
+
+    #[test]
+    fn quantized_error_is_within_half_a_step() {
+        let world = world();
+        let options = EncodeOptions::quantized(0.001, 0.01);
+        let data = encode(&world, &options).unwrap();
+        assert!(data.len() < encode(&world, &EncodeOptions::exact()).unwrap().len());
+
+        let decoded = decode(&data).unwrap();
+        for (a, b) in world.objects.iter().zip(decoded.objects.iter()) {
+            let position = a.get_com().sub(&b.get_com());
+            let velocity = a.get_velocity().sub(&b.get_velocity());
+            assert!(position.x.abs() <= 0.0005 + 1e-9 && position.y.abs() <= 0.0005 + 1e-9);
+            assert!(velocity.x.abs() <= 0.005 + 1e-9 && velocity.y.abs() <= 0.005 + 1e-9);
+        }
+
+        //Quantized values are already on the grid so encoding again is lossless
+        assert_eq!(encode(&decoded, &options).unwrap(), data);
+    }
+
+    #[test]
+    fn invalid_quantization_is_rejected() {
+        let world = world();
+        for &(position_step, velocity_step) in [(0.0, 0.01), (0.001, -1.0), (Real::NAN, 0.01), (0.001, Real::INFINITY)].iter() {
+            assert_eq!(encode(&world, &EncodeOptions::quantized(position_step, velocity_step)), Err(BinaryError::InvalidQuantization));
+        }
+    }
+
+    #[test]
+    fn delta_rebuilds_the_next_snapshot() {
+        let mut world = world();
+        let options = EncodeOptions::quantized(0.001, 0.01);
+        let mut base = encode(&world, &options).unwrap();
+        for _ in 0..20 {
+            world.update();
+            let frame = encode(&world, &options).unwrap();
+            let delta = encode_delta(&frame, &base);
+            assert!(delta.len() < frame.len());
+            assert_eq!(apply_delta(&delta, &base).unwrap(), frame);
+            base = frame;
+        }
+
+        //Identical snapshots give a delta that is only the header and one run
+        assert_eq!(apply_delta(&encode_delta(&base, &base), &base).unwrap(), base);
+    }
+
+    #[test]
+    fn delta_against_another_base_is_rejected() {
+        let world = world();
+        let exact = encode(&world, &EncodeOptions::exact()).unwrap();
+        let quantized = encode(&world, &EncodeOptions::quantized(0.001, 0.01)).unwrap();
+
+        let delta = encode_delta(&quantized, &exact);
+        assert_eq!(apply_delta(&delta, &quantized), Err(BinaryError::BaseMismatch));
+        assert_eq!(apply_delta(&quantized, &exact), Err(BinaryError::BadMagic));
+    }
+
+    #[test]
+    fn delta_with_an_impossible_length_is_rejected() {
+        let base = encode(&world(), &EncodeOptions::exact()).unwrap();
+        for &length in [u64::max_value(), (base.len() + 8 * MAX_DELTA_EXPANSION) as u64].iter() {
+            let mut writer = Writer {bytes: Vec::new(), quantization: None};
+            writer.bytes.extend_from_slice(DELTA_MAGIC);
+            writer.u8(FORMAT_VERSION);
+            let base_hash = hash(&base);
+            for i in 0..8 {
+                writer.u8((base_hash >> (i * 8)) as u8);
+            }
+            writer.varint(length);
+            //One unchanged run that would cover the whole length
+            writer.varint(length);
+            writer.varint(0);
+            assert_eq!(apply_delta(&writer.bytes, &base), Err(BinaryError::Malformed));
+        }
+    }
+
+    #[test]
+    fn deeply_nested_groups_are_rejected() {
+        let nested = |depth: usize| {
+            let mut group = Group::new();
+            group.add_object(Circle::new(1.0, Vec2D::new(0.0, 0.0), 0.5));
+            for _ in 1..depth {
+                let mut outer = Group::new();
+                outer.add_object(group);
+                group = outer;
+            }
+            let mut world = World::new(0.0, 0.01);
+            world.add_object(group);
+            encode(&world, &EncodeOptions::exact()).unwrap()
+        };
+
+        assert!(decode(&nested(MAX_GROUP_DEPTH)).is_ok());
+        assert_eq!(decode(&nested(MAX_GROUP_DEPTH + 1)).err(), Some(BinaryError::Malformed));
+    }
+
+    #[test]
+    fn state_view_reads_bodies_in_place() {
+        let world = world();
+        let data = encode(&world, &EncodeOptions::exact()).unwrap();
+        let view = StateView::parse(&data).unwrap();
+        assert_eq!(view.body_count, world.objects.len());
+
+        let bodies: Vec<BodyView> = view.bodies().map(|body| body.unwrap()).collect();
+        assert!(bodies[0].is_circle());
+        assert!(bodies[1].is_line());
+        assert!(bodies[2].is_polygon());
+        assert!(bodies[3].is_chain());
+        assert!(bodies[4].is_heightfield());
+        assert!(bodies[5].is_group());
+
+        //Payloads point into the encoded data instead of copying it
+        let start = data.as_ptr() as usize;
+        for body in bodies.iter() {
+            let payload = body.payload().as_ptr() as usize;
+            assert!(payload > start && payload < start + data.len());
+        }
+
+        assert_eq!(bodies[2].decode().unwrap().get_com(), world.objects[2].get_com());
+    }
+
+    #[test]
+    fn truncated_data_is_rejected() {
+        let data = encode(&world(), &EncodeOptions::quantized(0.001, 0.01)).unwrap();
+        for length in 0..data.len() {
+            assert!(decode(&data[..length]).is_err());
+        }
+
+        let mut newer = data.clone();
+        newer[STATE_MAGIC.len()] = FORMAT_VERSION + 1;
+        assert_eq!(decode(&newer).err(), Some(BinaryError::UnsupportedVersion(FORMAT_VERSION + 1)));
+    }
+}
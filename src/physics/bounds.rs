@@ -0,0 +1,169 @@
+use physics::Vec2D;
+use physics::Real;
+
+//Maximum number of items stored in a leaf of an AabbTree
+const LEAF_SIZE: usize = 4;
+
+//What World does with a body whose center of mass leaves World::bounds
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum OutOfBounds {
+    //Removed from the world
+    Despawn,
+    //Held at the edge with its outward velocity removed
+    Clamp,
+    //Removed from the world and handed back through World::out_of_bounds_events
+    Event
+}
+
+//Axis aligned bounding box
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Aabb {
+    pub min: Vec2D,
+    pub max: Vec2D
+}
+
+impl Aabb {
+    pub fn new(min: Vec2D, max: Vec2D) -> Aabb {
+        Aabb {min, max}
+    }
+
+    //Box containing nothing... overlaps no other box and is the identity for union
+    pub fn empty() -> Aabb {
+        Aabb::new(
+            Vec2D::new(Real::INFINITY, Real::INFINITY),
+            Vec2D::new(Real::NEG_INFINITY, Real::NEG_INFINITY)
+        )
+    }
+
+    pub fn from_points(points: &[Vec2D]) -> Aabb {
+        let mut bounds = Aabb::empty();
+        for p in points.iter() {
+            bounds.min = Vec2D::new(bounds.min.x.min(p.x), bounds.min.y.min(p.y));
+            bounds.max = Vec2D::new(bounds.max.x.max(p.x), bounds.max.y.max(p.y));
+        }
+
+        bounds
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb::new(
+            Vec2D::new(self.min.x.min(other.min.x), self.min.y.min(other.min.y)),
+            Vec2D::new(self.max.x.max(other.max.x), self.max.y.max(other.max.y))
+        )
+    }
+
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        self.min.x <= other.max.x && self.max.x >= other.min.x &&
+            self.min.y <= other.max.y && self.max.y >= other.min.y
+    }
+
+    pub fn contains_point(&self, point: &Vec2D) -> bool {
+        point.x >= self.min.x && point.x <= self.max.x && point.y >= self.min.y && point.y <= self.max.y
+    }
+
+    pub fn translate(&self, displacement: &Vec2D) -> Aabb {
+        Aabb::new(self.min.add(displacement), self.max.add(displacement))
+    }
+
+    //Moves a point into the box treating it as a torus (ex. a periodic world)
+    pub fn wrap(&self, point: &Vec2D) -> Vec2D {
+        let width = self.max.x - self.min.x;
+        let height = self.max.y - self.min.y;
+        Vec2D::new(
+            point.x - width * ((point.x - self.min.x) / width).floor(),
+            point.y - height * ((point.y - self.min.y) / height).floor()
+        )
+    }
+
+    //Shortest equivalent of offset when the box wraps around... minimum image convention
+    pub fn minimum_image(&self, offset: &Vec2D) -> Vec2D {
+        let width = self.max.x - self.min.x;
+        let height = self.max.y - self.min.y;
+        Vec2D::new(offset.x - width * (offset.x / width).round(), offset.y - height * (offset.y / height).round())
+    }
+}
+
+#[derive(Clone)]
+struct AabbNode {
+    bounds: Aabb,
+    //Range of item indices covered by this node
+    start: usize,
+    end: usize,
+    //Indices of the child nodes... None for leaves
+    children: Option<(usize, usize)>
+}
+
+//Static bounding volume hierarchy over a list of boxes
+//Items are split by index so the tree works best when neighbouring items are close in space (ex. polyline segments)
+#[derive(Clone)]
+pub struct AabbTree {
+    nodes: Vec<AabbNode>
+}
+
+impl AabbTree {
+    pub fn new(items: &[Aabb]) -> AabbTree {
+        let mut tree = AabbTree {nodes: Vec::new()};
+        if !items.is_empty() {
+            tree.build(items, 0, items.len());
+        }
+
+        tree
+    }
+
+    //Returns index of the node created for items[start..end]
+    fn build(&mut self, items: &[Aabb], start: usize, end: usize) -> usize {
+        let mut bounds = items[start];
+        for item in items[start..end].iter() {
+            bounds = bounds.union(item);
+        }
+
+        let index = self.nodes.len();
+        self.nodes.push(AabbNode {bounds, start, end, children: None});
+
+        if end - start > LEAF_SIZE {
+            let middle = start + (end - start) / 2;
+            let left = self.build(items, start, middle);
+            let right = self.build(items, middle, end);
+            self.nodes[index].children = Some((left, right));
+        }
+
+        index
+    }
+
+    //Bounds of every item in the tree
+    pub fn bounds(&self) -> Option<Aabb> {
+        self.nodes.first().map(|node| node.bounds)
+    }
+
+    //Pushes the index of every item whose leaf overlaps bounds
+    //Leaf items are not tested individually so callers should still check the candidates
+    pub fn query(&self, bounds: &Aabb, results: &mut Vec<usize>) {
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !node.bounds.overlaps(bounds) {
+                continue;
+            }
+
+            match node.children {
+                Some((left, right)) => {
+                    stack.push(left);
+                    stack.push(right);
+                },
+                None => results.extend(node.start..node.end)
+            }
+        }
+    }
+
+    pub fn translate(&mut self, displacement: &Vec2D) {
+        for node in self.nodes.iter_mut() {
+            node.bounds = node.bounds.translate(displacement);
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use physics::Vec2D;
+use physics::Real;
+use physics::geometry;
+use physics::geometry::PolygonError;
+use physics::bounds::Aabb;
+use physics::shapes::*;
+
+use renderer::RenderableObject;
+
+use std::f64::consts::PI;
+
+//Number of sides of the polygon standing in for a circle when clipping
+const CIRCLE_SEGMENTS: usize = 32;
+
+//Part of a body that lies inside a water volume
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Submersion {
+    pub area: Real,
+    //Center of the submerged part... this is where the buoyant force acts
+    pub centroid: Vec2D
+}
+
+//Region of still or flowing liquid that pushes overlapping bodies up and slows them down
+//Bodies are not simulated inside the liquid... use physics::fluid for that
+//Bodies don't rotate so there is no angular drag and the buoyant force at the submerged centroid only changes linear velocity
+#[derive(Clone)]
+pub struct WaterVolume {
+    //Counter-clockwise convex outline of the region
+    pub points: Vec<Vec2D>,
+    pub density: Real,
+    //Drag per unit of submerged area against motion relative to the liquid
+    pub linear_drag: Real,
+    //Acceleration of gravity inside the region... buoyancy pushes the opposite way
+    pub gravity: Vec2D,
+    //Velocity of the liquid (ex. a river current)
+    pub flow: Vec2D,
+    pub color: [f32; 4]
+}
+
+//Combines two submerged parts into one
+fn merge(a: Option<Submersion>, b: Option<Submersion>) -> Option<Submersion> {
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            let area = a.area + b.area;
+            let centroid = a.centroid.mult(a.area).add(&b.centroid.mult(b.area)).mult(1.0 / area);
+            Some(Submersion {area, centroid})
+        },
+        (a, None) => a,
+        (None, b) => b
+    }
+}
+
+impl WaterVolume {
+    //Points must describe a convex polygon since bodies are clipped against it
+    pub fn new(points: Vec<Vec2D>, density: Real) -> Result<WaterVolume, PolygonError> {
+        let points = geometry::validate_convex(&points)?;
+
+        Ok(WaterVolume {
+            points,
+            density,
+            linear_drag: 0.5,
+            gravity: Vec2D::new(0.0, -9.8),
+            flow: Vec2D::new(0.0, 0.0),
+            color: [0.2, 0.4, 1.0, 0.4]
+        })
+    }
+
+    //Axis aligned pool whose surface is the top edge
+    pub fn new_rectangle(min: Vec2D, max: Vec2D, density: Real) -> Result<WaterVolume, PolygonError> {
+        WaterVolume::new(vec![
+            min,
+            Vec2D::new(max.x, min.y),
+            max,
+            Vec2D::new(min.x, max.y)
+        ], density)
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        Aabb::from_points(&self.points)
+    }
+
+    fn submerged_polygon(&self, points: &[Vec2D]) -> Option<Submersion> {
+        let clipped = geometry::clip_convex(points, &self.points);
+        if clipped.len() < 3 {
+            return None;
+        }
+
+        let area = geometry::signed_area(&clipped).abs();
+        if area < geometry::EPSILON {
+            return None;
+        }
+
+        Some(Submersion {area, centroid: geometry::centroid(&clipped)})
+    }
+
+    //Returns the part of body inside the region... shapes without area (lines, chains and heightfields) never float
+    pub fn submerged(&self, body: &RenderableObject) -> Option<Submersion> {
+        if !body.bounds().overlaps(&self.bounds()) {
+            return None;
+        }
+
+        if body.as_any().is::<Circle>() {
+            let circle: &Circle = body.as_any().downcast_ref::<Circle>().unwrap();
+
+            //Stretch the polygon so its area matches the circle
+            let n = CIRCLE_SEGMENTS as Real;
+            let angle = 2.0 * PI as Real / n;
+            let radius = circle.radius * (circle.area() / (0.5 * n * circle.radius * circle.radius * angle.sin())).sqrt();
+            let points: Vec<Vec2D> = (0..CIRCLE_SEGMENTS)
+                .map(|i| circle.center.add(&Vec2D::new((angle * i as Real).cos(), (angle * i as Real).sin()).mult(radius)))
+                .collect();
+            return self.submerged_polygon(&points);
+        } else if body.as_any().is::<Polygon>() {
+            let polygon: &Polygon = body.as_any().downcast_ref::<Polygon>().unwrap();
+            return self.submerged_polygon(&polygon.points);
+        } else if body.as_any().is::<Group>() {
+            let group: &Group = body.as_any().downcast_ref::<Group>().unwrap();
+            let mut submersion: Option<Submersion> = None;
+            for object in group.objects.iter() {
+                submersion = merge(submersion, self.submerged(&**object));
+            }
+            return submersion;
+        }
+
+        return None;
+    }
+
+    //Applies buoyancy and drag to every non-static body over timestep
+    pub fn apply(&self, timestep: Real, objects: &mut [Box<RenderableObject>]) {
+        for obj in objects.iter_mut() {
+            if obj.get_static() || obj.get_mass() <= 0.0 {
+                continue;
+            }
+
+            let submersion = match self.submerged(&**obj) {
+                Some(submersion) => submersion,
+                None => continue
+            };
+
+            //Archimedes' principle... the weight of the displaced liquid pushes up at the submerged centroid
+            //Bodies only translate so the force acts on the center of mass
+            let mass = obj.get_mass();
+            let buoyancy = self.gravity.mult(-self.density * submersion.area);
+            let mut velocity = obj.get_velocity().add(&buoyancy.mult(timestep / mass));
+
+            //Drag can at most bring the body to rest relative to the liquid
+            let relative_velocity = velocity.sub(&self.flow);
+            let drag = (self.linear_drag * submersion.area * timestep / mass).min(1.0);
+            velocity = velocity.sub(&relative_velocity.mult(drag));
+
+            obj.set_velocity(&velocity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concave_outline_is_rejected() {
+        let points = vec![
+            Vec2D::new(0.0, 0.0),
+            Vec2D::new(2.0, 0.0),
+            Vec2D::new(2.0, 2.0),
+            Vec2D::new(1.0, 1.0),
+            Vec2D::new(0.0, 2.0)
+        ];
+        assert_eq!(WaterVolume::new(points, 1.0).err(), Some(PolygonError::NotConvex));
+    }
+
+    #[test]
+    fn clockwise_outline_is_reversed() {
+        let water = WaterVolume::new_rectangle(Vec2D::new(0.0, 2.0), Vec2D::new(2.0, 0.0), 1.0).unwrap();
+        assert!(geometry::signed_area(&water.points) > 0.0);
+    }
+}
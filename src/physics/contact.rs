@@ -0,0 +1,225 @@
+use physics::Vec2D;
+use physics::Real;
+use physics::geometry;
+use physics::bounds::Aabb;
+use physics::shapes::*;
+
+use renderer::RenderableObject;
+
+//Contact between a round point and a body
+pub struct PointContact {
+    //Unit vector pointing out of the body
+    pub normal: Vec2D,
+    //Distance the point has to move along normal to stop overlapping
+    pub depth: Real
+}
+
+//Returns contact between a point with the given radius and a body if they overlap
+pub fn point_contact(point: &Vec2D, radius: Real, body: &RenderableObject) -> Option<PointContact> {
+    if body.as_any().is::<Circle>() {
+        let circle: &Circle = body.as_any().downcast_ref::<Circle>().unwrap();
+        let offset = point.sub(&circle.center);
+        let distance = offset.mag();
+        if distance >= circle.radius + radius {
+            return None;
+        }
+
+        let normal = offset.try_unit().unwrap_or(Vec2D::new(0.0, 1.0));
+        return Some(PointContact {normal, depth: circle.radius + radius - distance});
+    } else if body.as_any().is::<Line>() {
+        let line: &Line = body.as_any().downcast_ref::<Line>().unwrap();
+        return segment_contact(point, radius, &line.start_point, &line.end_point);
+    } else if body.as_any().is::<Polygon>() {
+        let polygon: &Polygon = body.as_any().downcast_ref::<Polygon>().unwrap();
+        return polygon_contact(point, radius, &polygon.points);
+    } else if body.as_any().is::<Chain>() {
+        let chain: &Chain = body.as_any().downcast_ref::<Chain>().unwrap();
+        let extent = Vec2D::new(radius, radius);
+        let mut deepest: Option<PointContact> = None;
+        for i in chain.query(&Aabb::new(point.sub(&extent), point.add(&extent))) {
+            let (start, end) = chain.segment(i);
+            deepest = deeper(deepest, segment_contact(point, radius, &start, &end));
+        }
+        return deepest;
+    } else if body.as_any().is::<Heightfield>() {
+        let heightfield: &Heightfield = body.as_any().downcast_ref::<Heightfield>().unwrap();
+
+        //Points below the surface are pushed straight out
+        if let Some(height) = heightfield.height_at(point.x) {
+            if point.y < height {
+                let (i, _) = heightfield.column_range(point.x, point.x).unwrap();
+                let normal = heightfield.column_normal(i).unit();
+                return Some(PointContact {normal, depth: (height - point.y) * normal.y + radius});
+            }
+        }
+
+        let (first, last) = match heightfield.column_range(point.x - radius, point.x + radius) {
+            Some(range) => range,
+            None => return None
+        };
+        let mut deepest: Option<PointContact> = None;
+        for i in first..(last + 1) {
+            let (start, end) = heightfield.column(i);
+            deepest = deeper(deepest, segment_contact(point, radius, &start, &end));
+        }
+        return deepest;
+    } else if body.as_any().is::<Group>() {
+        let group: &Group = body.as_any().downcast_ref::<Group>().unwrap();
+        let mut deepest: Option<PointContact> = None;
+        for object in group.objects.iter() {
+            deepest = deeper(deepest, point_contact(point, radius, &**object));
+        }
+        return deepest;
+    }
+
+    return None;
+}
+
+fn deeper(current: Option<PointContact>, candidate: Option<PointContact>) -> Option<PointContact> {
+    match (current, candidate) {
+        (Some(current), Some(candidate)) => {
+            if candidate.depth > current.depth { Some(candidate) } else { Some(current) }
+        },
+        (current, None) => current,
+        (None, candidate) => candidate
+    }
+}
+
+fn segment_contact(point: &Vec2D, radius: Real, start: &Vec2D, end: &Vec2D) -> Option<PointContact> {
+    let closest_point = geometry::closest_point_on_segment(point, start, end);
+    let offset = point.sub(&closest_point);
+    let distance = offset.mag();
+    if distance >= radius {
+        return None;
+    }
+
+    //A point exactly on the segment is pushed to the left side of the segment
+    let normal = match offset.try_unit() {
+        Some(normal) => normal,
+        None => end.sub(start).perp().try_unit().unwrap_or(Vec2D::new(0.0, 1.0))
+    };
+
+    Some(PointContact {normal, depth: radius - distance})
+}
+
+//Points must be a counter-clockwise convex polygon
+fn polygon_contact(point: &Vec2D, radius: Real, points: &[Vec2D]) -> Option<PointContact> {
+    if !geometry::point_in_convex(point, points) {
+        let mut deepest: Option<PointContact> = None;
+        for i in 0..points.len() {
+            deepest = deeper(deepest, segment_contact(point, radius, &points[i], &points[(i + 1) % points.len()]));
+        }
+        return deepest;
+    }
+
+    //Inside the polygon so leave through the closest edge
+    let mut closest: Option<PointContact> = None;
+    for i in 0..points.len() {
+        let edge = points[(i + 1) % points.len()].sub(&points[i]);
+        let normal = match edge.perp().mult(-1.0).try_unit() {
+            Some(normal) => normal,
+            None => continue
+        };
+        let depth = radius - point.sub(&points[i]).dot(&normal);
+        let is_closer = match closest {
+            Some(ref contact) => depth < contact.depth,
+            None => true
+        };
+        if is_closer {
+            closest = Some(PointContact {normal, depth});
+        }
+    }
+
+    closest
+}
+
+//Pushes a point with the given radius out of every body and removes the velocity moving into them
+//The opposite impulse is applied to non-static bodies... a mass of zero makes the collision one-way
+pub fn collide_point(position: &mut Vec2D, velocity: &mut Vec2D, mass: Real, radius: Real, restitution: Real,
+                     objects: &mut [Box<RenderableObject>]) {
+    for i in 0..objects.len() {
+        if objects[i].get_static() {
+            continue;
+        }
+
+        let contact = match find_contact(position, radius, &*objects[i]) {
+            Some(contact) => contact,
+            None => continue
+        };
+
+        //A point squeezed between a moving body and a wall keeps overlapping the body instead of being pushed through the wall
+        if !is_push_blocked(position, &contact, radius, objects) {
+            *position = position.add(&contact.normal.mult(contact.depth));
+        }
+        resolve_velocity(velocity, mass, restitution, &contact.normal, &mut *objects[i]);
+    }
+
+    //Static bodies are resolved last so they always win
+    for obj in objects.iter_mut() {
+        if !obj.get_static() {
+            continue;
+        }
+
+        if let Some(contact) = find_contact(position, radius, &**obj) {
+            *position = position.add(&contact.normal.mult(contact.depth));
+            resolve_velocity(velocity, mass, restitution, &contact.normal, &mut **obj);
+        }
+    }
+}
+
+fn find_contact(position: &Vec2D, radius: Real, obj: &RenderableObject) -> Option<PointContact> {
+    let extent = Vec2D::new(radius, radius);
+    if !obj.bounds().overlaps(&Aabb::new(position.sub(&extent), position.add(&extent))) {
+        return None;
+    }
+
+    point_contact(position, radius, obj)
+}
+
+//Deepest overlap between the point and any static body
+fn static_depth(position: &Vec2D, radius: Real, objects: &[Box<RenderableObject>]) -> Real {
+    let mut depth = 0.0;
+    for obj in objects.iter() {
+        if !obj.get_static() {
+            continue;
+        }
+
+        if let Some(contact) = find_contact(position, radius, &**obj) {
+            depth = contact.depth.max(depth);
+        }
+    }
+
+    depth
+}
+
+//Checks whether pushing the point out of a contact would move it deeper into a static body
+//The push is sampled every radius so thin walls can't be skipped over
+fn is_push_blocked(position: &Vec2D, contact: &PointContact, radius: Real, objects: &[Box<RenderableObject>]) -> bool {
+    let start_depth = static_depth(position, radius, objects);
+    let samples = if radius > 0.0 { (contact.depth / radius).ceil().max(1.0) as usize } else { 1 };
+    for i in 1..(samples + 1) {
+        let sample = position.add(&contact.normal.mult(contact.depth * i as Real / samples as Real));
+        if static_depth(&sample, radius, objects) > start_depth + geometry::EPSILON {
+            return true;
+        }
+    }
+
+    false
+}
+
+//Removes the velocity moving into the body along normal
+fn resolve_velocity(velocity: &mut Vec2D, mass: Real, restitution: Real, normal: &Vec2D, obj: &mut RenderableObject) {
+    let body_velocity = obj.get_velocity();
+    let approach = velocity.sub(&body_velocity).dot(normal);
+    if approach >= 0.0 {
+        return;
+    }
+
+    if mass > 0.0 && !obj.get_static() && obj.get_mass() > 0.0 {
+        let impulse = -(1.0 + restitution) * approach / (1.0 / mass + 1.0 / obj.get_mass());
+        *velocity = velocity.add(&normal.mult(impulse / mass));
+        obj.set_velocity(&body_velocity.sub(&normal.mult(impulse / obj.get_mass())));
+    } else {
+        *velocity = velocity.sub(&normal.mult((1.0 + restitution) * approach));
+    }
+}
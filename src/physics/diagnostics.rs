@@ -0,0 +1,209 @@
+use physics::Vec2D;
+use physics::Real;
+use physics::World;
+
+use std::fmt;
+
+//Conserved quantities of the world at one step
+//Angular momentum is taken about the origin... bodies don't spin so only orbital motion counts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConservationReport {
+    pub step: usize,
+    pub kinetic_energy: Real,
+    //Uniform gravity of soft bodies, verlet bodies and fluids, mutual gravity and stretched springs
+    pub potential_energy: Real,
+    pub linear_momentum: Vec2D,
+    pub angular_momentum: Real,
+    //Sums of the magnitudes of every contribution... used to judge drift of quantities that are near zero
+    pub momentum_scale: Real,
+    pub angular_momentum_scale: Real
+}
+
+impl ConservationReport {
+    pub fn total_energy(&self) -> Real {
+        self.kinetic_energy + self.potential_energy
+    }
+}
+
+//Sums the conserved quantities over every moving part of the world
+//Static bodies are immovable and particle systems are massless so neither contributes
+pub fn measure(world: &World) -> ConservationReport {
+    let mut report = ConservationReport {
+        step: 0,
+        kinetic_energy: 0.0,
+        potential_energy: 0.0,
+        linear_momentum: Vec2D::new(0.0, 0.0),
+        angular_momentum: 0.0,
+        momentum_scale: 0.0,
+        angular_momentum_scale: 0.0
+    };
+
+    {
+        let mut add = |position: &Vec2D, velocity: &Vec2D, mass: Real| {
+            if mass <= 0.0 {
+                return;
+            }
+
+            let momentum = velocity.mult(mass);
+            report.kinetic_energy += 0.5 * mass * velocity.dot(velocity);
+            report.linear_momentum = report.linear_momentum.add(&momentum);
+            report.angular_momentum += position.cross(&momentum);
+            report.momentum_scale += momentum.mag();
+            report.angular_momentum_scale += position.mag() * momentum.mag();
+        };
+
+        for obj in world.objects.iter() {
+            if !obj.get_static() {
+                add(&obj.get_com(), &obj.get_velocity(), obj.get_mass());
+            }
+        }
+        for body in world.soft_bodies.iter() {
+            for point in body.points.iter() {
+                add(&point.position, &point.velocity, point.mass);
+            }
+        }
+        for body in world.verlet_bodies.iter() {
+            for (i, particle) in body.particles.iter().enumerate() {
+                add(&particle.position, &body.velocity(i, world.timestep), particle.mass);
+            }
+        }
+        for fluid in world.fluids.iter() {
+            for (position, velocity) in fluid.positions.iter().zip(fluid.velocities.iter()) {
+                add(position, velocity, fluid.particle_mass);
+            }
+        }
+    }
+
+    //Potential of a uniform acceleration is -m a . r
+    for body in world.soft_bodies.iter() {
+        for point in body.points.iter() {
+            report.potential_energy -= point.mass * body.acceleration.dot(&point.position);
+        }
+        for spring in body.springs.iter() {
+            let stretch = body.points[spring.a].position.sub(&body.points[spring.b].position).mag() - spring.rest_length;
+            report.potential_energy += 0.5 * spring.stiffness * stretch * stretch;
+        }
+    }
+    for body in world.verlet_bodies.iter() {
+        for particle in body.particles.iter() {
+            report.potential_energy -= particle.mass * body.acceleration.dot(&particle.position);
+        }
+        //Compliance is the inverse of stiffness... rigid constraints store no energy
+        for constraint in body.constraints.iter() {
+            if constraint.compliance > 0.0 {
+                let stretch = body.particles[constraint.a].position.sub(&body.particles[constraint.b].position).mag() - constraint.rest_length;
+                report.potential_energy += 0.5 * stretch * stretch / constraint.compliance;
+            }
+        }
+    }
+    for fluid in world.fluids.iter() {
+        for position in fluid.positions.iter() {
+            report.potential_energy -= fluid.particle_mass * fluid.gravity.dot(position);
+        }
+    }
+    if let Some(ref gravity) = world.mutual_gravity {
+        report.potential_energy += gravity.potential_energy(&world.objects);
+    }
+
+    report
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quantity {
+    Energy,
+    LinearMomentum,
+    AngularMomentum
+}
+
+//Raised when a quantity first drifts further than the tolerance from the baseline
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DriftWarning {
+    pub step: usize,
+    pub quantity: Quantity,
+    //Drift relative to the baseline
+    pub drift: Real
+}
+
+impl fmt::Display for DriftWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "step {}: {:?} drifted by {:.3}%", self.step, self.quantity, self.drift * 100.0)
+    }
+}
+
+//Measures the world after every step and compares it against the first measurement
+//Collisions with static bodies and friction don't conserve momentum so tolerances should be set with the scene in mind
+pub struct ConservationMonitor {
+    //Largest allowed relative drift of each quantity
+    pub energy_tolerance: Real,
+    pub momentum_tolerance: Real,
+    pub angular_momentum_tolerance: Real,
+    pub baseline: Option<ConservationReport>,
+    pub history: Vec<ConservationReport>,
+    pub warnings: Vec<DriftWarning>,
+    //Quantities currently past their tolerance... a warning is only raised when a quantity crosses it
+    exceeded: [bool; 3],
+    step: usize
+}
+
+impl ConservationMonitor {
+    pub fn new(tolerance: Real) -> ConservationMonitor {
+        ConservationMonitor {
+            energy_tolerance: tolerance,
+            momentum_tolerance: tolerance,
+            angular_momentum_tolerance: tolerance,
+            baseline: None,
+            history: Vec::new(),
+            warnings: Vec::new(),
+            exceeded: [false; 3],
+            step: 0
+        }
+    }
+
+    //Uses the next measurement as the baseline... call after adding or removing bodies
+    pub fn reset_baseline(&mut self) {
+        self.baseline = None;
+        self.exceeded = [false; 3];
+    }
+
+    //Relative drift of every quantity from the baseline
+    pub fn drift(&self, report: &ConservationReport) -> Option<[Real; 3]> {
+        let baseline = match self.baseline {
+            Some(baseline) => baseline,
+            None => return None
+        };
+
+        let relative = |change: Real, scale: Real| if scale > 0.0 { change / scale } else if change > 0.0 { Real::INFINITY } else { 0.0 };
+        Some([
+            relative((report.total_energy() - baseline.total_energy()).abs(), baseline.kinetic_energy.abs() + baseline.potential_energy.abs()),
+            relative(report.linear_momentum.sub(&baseline.linear_momentum).mag(), baseline.momentum_scale),
+            relative((report.angular_momentum - baseline.angular_momentum).abs(), baseline.angular_momentum_scale)
+        ])
+    }
+
+    //Measures world and returns the warnings raised by this step
+    pub fn sample(&mut self, world: &World) -> &[DriftWarning] {
+        let mut report = measure(world);
+        report.step = self.step;
+        self.step += 1;
+
+        if self.baseline.is_none() {
+            self.baseline = Some(report);
+        }
+
+        let first_warning = self.warnings.len();
+        if let Some(drift) = self.drift(&report) {
+            let tolerances = [self.energy_tolerance, self.momentum_tolerance, self.angular_momentum_tolerance];
+            let quantities = [Quantity::Energy, Quantity::LinearMomentum, Quantity::AngularMomentum];
+            for k in 0..3 {
+                let exceeded = drift[k] > tolerances[k];
+                if exceeded && !self.exceeded[k] {
+                    self.warnings.push(DriftWarning {step: report.step, quantity: quantities[k], drift: drift[k]});
+                }
+                self.exceeded[k] = exceeded;
+            }
+        }
+
+        self.history.push(report);
+        &self.warnings[first_warning..]
+    }
+}
@@ -0,0 +1,88 @@
+use physics::Vec2D;
+use physics::Real;
+
+use renderer::RenderableObject;
+
+//Coulomb forces between charged bodies plus uniform electric and magnetic fields
+#[derive(Clone)]
+pub struct Electromagnetism {
+    //Coulomb constant k in F = k q1 q2 / r^2
+    pub coulomb: Real,
+    //Added to distances so close encounters don't produce huge forces
+    pub softening: Real,
+    //Uniform electric field
+    pub electric_field: Vec2D,
+    //Uniform magnetic field pointing out of the screen... negative values point into the screen
+    pub magnetic_field: Real
+}
+
+impl Electromagnetism {
+    pub fn new(coulomb: Real) -> Electromagnetism {
+        Electromagnetism {
+            coulomb,
+            softening: 0.01,
+            electric_field: Vec2D::new(0.0, 0.0),
+            magnetic_field: 0.0
+        }
+    }
+
+    //Electric force on every body from the other charges and the electric field
+    //Like charges repel and opposite charges attract
+    pub fn electric_forces(&self, positions: &[Vec2D], charges: &[Real]) -> Vec<Vec2D> {
+        let mut forces: Vec<Vec2D> = charges.iter().map(|&charge| self.electric_field.mult(charge)).collect();
+        for i in 0..positions.len() {
+            if charges[i] == 0.0 {
+                continue;
+            }
+
+            for j in (i + 1)..positions.len() {
+                if charges[j] == 0.0 {
+                    continue;
+                }
+
+                let offset = positions[i].sub(&positions[j]);
+                let distance_squared = offset.dot(&offset) + self.softening * self.softening;
+                if distance_squared <= 0.0 {
+                    continue;
+                }
+
+                let on_i = offset.mult(self.coulomb * charges[i] * charges[j] / (distance_squared * distance_squared.sqrt()));
+                forces[i] = forces[i].add(&on_i);
+                forces[j] = forces[j].sub(&on_i);
+            }
+        }
+
+        forces
+    }
+
+    //Velocity after the Lorentz force q v x B acts for timestep
+    //The force is always perpendicular to the velocity so the velocity is rotated instead of stepped
+    //This keeps the speed constant and cyclotron orbits closed
+    pub fn magnetic_rotation(&self, velocity: &Vec2D, charge: Real, mass: Real, timestep: Real) -> Vec2D {
+        if mass <= 0.0 || charge == 0.0 || self.magnetic_field == 0.0 {
+            return *velocity;
+        }
+
+        //Cyclotron frequency... positive charges circle clockwise in a field pointing out of the screen
+        let angular_velocity = -charge * self.magnetic_field / mass;
+        velocity.rotate(angular_velocity * timestep)
+    }
+
+    //Static and massless bodies exert electric forces but aren't moved by them
+    pub fn apply(&self, timestep: Real, objects: &mut [Box<RenderableObject>]) {
+        let positions: Vec<Vec2D> = objects.iter().map(|obj| obj.get_com()).collect();
+        let charges: Vec<Real> = objects.iter().map(|obj| obj.get_charge()).collect();
+        let forces = self.electric_forces(&positions, &charges);
+
+        for (obj, force) in objects.iter_mut().zip(forces.iter()) {
+            let mass = obj.get_mass();
+            if obj.get_static() || mass <= 0.0 {
+                continue;
+            }
+
+            let velocity = obj.get_velocity().add(&force.mult(timestep / mass));
+            let velocity = self.magnetic_rotation(&velocity, obj.get_charge(), mass, timestep);
+            obj.set_velocity(&velocity);
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use physics::Vec2D;
+use physics::Real;
+use physics::bounds::Aabb;
+
+use renderer::RenderableObject;
+
+//Region a force field acts in... bodies are tested by their center of mass
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldArea {
+    Everywhere,
+    Circle(Vec2D, Real),
+    Box(Aabb)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldKind {
+    //Gravity well pulling towards center... softening stops the pull from blowing up near the center
+    Attractor {center: Vec2D, strength: Real, softening: Real},
+    //Drags bodies towards the wind velocity... strength is the fraction of the difference removed per second
+    Wind {velocity: Vec2D, strength: Real},
+    //Swirls bodies counter-clockwise around center (clockwise for negative strength) with pull towards the center
+    Vortex {center: Vec2D, strength: Real, pull: Real, softening: Real},
+    //One-shot outward impulse that falls off linearly to zero at radius... removed after the step it is applied
+    Explosion {center: Vec2D, impulse: Real, radius: Real}
+}
+
+#[derive(Clone)]
+pub struct ForceField {
+    pub kind: FieldKind,
+    pub area: FieldArea,
+    //Only bodies whose category shares a bit with mask are affected
+    pub mask: u32,
+    pub enabled: bool
+}
+
+impl FieldArea {
+    pub fn contains(&self, point: &Vec2D) -> bool {
+        match *self {
+            FieldArea::Everywhere => true,
+            FieldArea::Circle(center, radius) => point.sub(&center).mag() <= radius,
+            FieldArea::Box(bounds) => bounds.contains_point(point)
+        }
+    }
+}
+
+impl ForceField {
+    //Field affecting every category
+    pub fn new(kind: FieldKind, area: FieldArea) -> ForceField {
+        ForceField {kind, area, mask: u32::max_value(), enabled: true}
+    }
+
+    pub fn attractor(center: Vec2D, strength: Real, radius: Real) -> ForceField {
+        ForceField::new(FieldKind::Attractor {center, strength, softening: 0.5}, FieldArea::Circle(center, radius))
+    }
+
+    pub fn wind(velocity: Vec2D, strength: Real, area: Aabb) -> ForceField {
+        ForceField::new(FieldKind::Wind {velocity, strength}, FieldArea::Box(area))
+    }
+
+    pub fn vortex(center: Vec2D, strength: Real, radius: Real) -> ForceField {
+        ForceField::new(FieldKind::Vortex {center, strength, pull: 0.0, softening: 0.5}, FieldArea::Circle(center, radius))
+    }
+
+    pub fn explosion(center: Vec2D, impulse: Real, radius: Real) -> ForceField {
+        ForceField::new(FieldKind::Explosion {center, impulse, radius}, FieldArea::Circle(center, radius))
+    }
+
+    pub fn is_one_shot(&self) -> bool {
+        match self.kind {
+            FieldKind::Explosion {..} => true,
+            _ => false
+        }
+    }
+
+    //Change in velocity of a body over timestep
+    pub fn velocity_change(&self, position: &Vec2D, velocity: &Vec2D, mass: Real, timestep: Real) -> Vec2D {
+        match self.kind {
+            FieldKind::Attractor {center, strength, softening} => {
+                let offset = center.sub(position);
+                let distance_squared = offset.dot(&offset) + softening * softening;
+                offset.normalize_or_zero().mult(strength / distance_squared * timestep)
+            },
+            FieldKind::Wind {velocity: wind, strength} => {
+                //Can at most bring the body up to the wind speed
+                wind.sub(velocity).mult((strength * timestep).min(1.0))
+            },
+            FieldKind::Vortex {center, strength, pull, softening} => {
+                let offset = position.sub(&center);
+                let distance = (offset.dot(&offset) + softening * softening).sqrt();
+                let direction = offset.normalize_or_zero();
+                direction.perp().mult(strength / distance * timestep).sub(&direction.mult(pull * timestep))
+            },
+            FieldKind::Explosion {center, impulse, radius} => {
+                if mass <= 0.0 || radius <= 0.0 {
+                    return Vec2D::new(0.0, 0.0);
+                }
+
+                let offset = position.sub(&center);
+                let falloff = (1.0 - offset.mag() / radius).max(0.0);
+                offset.normalize_or_zero().mult(impulse * falloff / mass)
+            }
+        }
+    }
+
+    //Pushes every matching non-static body inside the area
+    pub fn apply(&self, timestep: Real, objects: &mut [Box<RenderableObject>]) {
+        if !self.enabled {
+            return;
+        }
+
+        for obj in objects.iter_mut() {
+            if obj.get_static() || obj.get_category() & self.mask == 0 {
+                continue;
+            }
+
+            let position = obj.get_com();
+            if !self.area.contains(&position) {
+                continue;
+            }
+
+            let velocity = obj.get_velocity();
+            let change = self.velocity_change(&position, &velocity, obj.get_mass(), timestep);
+            obj.set_velocity(&velocity.add(&change));
+        }
+    }
+}
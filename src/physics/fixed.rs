@@ -0,0 +1,90 @@
+use physics::Real;
+
+use std::ops;
+
+const FRACTIONAL_BITS: u32 = 32;
+const ONE: i64 = 1 << FRACTIONAL_BITS;
+
+//f64 has a 53 bit mantissa so 32 fractional bits leave 21 bits for the integer part
+pub const MAX_EXACT: f64 = (1u64 << 21) as f64;
+
+//Q32.32 fixed point number
+//Arithmetic is done with integers so results are bit-identical on every machine and compiler
+//Vec2D converts through f64, which holds every Q32.32 value exactly only below MAX_EXACT in magnitude
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Fixed(pub i64);
+
+impl Fixed {
+    //Rounds to the nearest representable value... out of range values saturate and NaN becomes zero
+    //Values at or above MAX_EXACT in magnitude were already rounded by f64 and lose fractional bits
+    pub fn from_real(value: Real) -> Fixed {
+        Fixed((value as f64 * ONE as f64).round() as i64)
+    }
+
+    pub fn to_real(self) -> Real {
+        (self.0 as f64 / ONE as f64) as Real
+    }
+
+    //Square root rounded down... negative numbers give zero
+    pub fn sqrt(self) -> Fixed {
+        if self.0 <= 0 {
+            return Fixed(0);
+        }
+
+        //sqrt(bits / 2^32) * 2^32 = sqrt(bits * 2^32)
+        Fixed(isqrt((self.0 as u128) << FRACTIONAL_BITS) as i64)
+    }
+}
+
+//Integer square root using Newton's method
+fn isqrt(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
+
+    let mut x = n;
+    let mut y = (x + 1) / 2;
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+
+    x
+}
+
+//Converts a wide intermediate result back to 64 bits, saturating on overflow
+fn saturate(value: i128) -> Fixed {
+    if value > i64::MAX as i128 {
+        Fixed(i64::MAX)
+    } else if value < i64::MIN as i128 {
+        Fixed(i64::MIN)
+    } else {
+        Fixed(value as i64)
+    }
+}
+
+impl ops::Add for Fixed {
+    type Output = Fixed;
+
+    fn add(self, other: Fixed) -> Fixed {
+        Fixed(self.0.saturating_add(other.0))
+    }
+}
+
+impl ops::Sub for Fixed {
+    type Output = Fixed;
+
+    fn sub(self, other: Fixed) -> Fixed {
+        Fixed(self.0.saturating_sub(other.0))
+    }
+}
+
+impl ops::Mul for Fixed {
+    type Output = Fixed;
+
+    fn mul(self, other: Fixed) -> Fixed {
+        //Round to nearest by adding half of the discarded range before shifting
+        let product = self.0 as i128 * other.0 as i128;
+        saturate((product + (1 << (FRACTIONAL_BITS - 1))) >> FRACTIONAL_BITS)
+    }
+}
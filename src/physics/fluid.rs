@@ -0,0 +1,312 @@
+use physics::Vec2D;
+use physics::Real;
+use physics::contact;
+use physics::bounds::Aabb;
+
+use renderer::RenderableObject;
+
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+//Uniform grid used to find particles within the smoothing radius of each other
+#[derive(Clone)]
+pub struct NeighborGrid {
+    pub cell_size: Real,
+    cells: HashMap<(i64, i64), Vec<usize>>
+}
+
+impl NeighborGrid {
+    pub fn new(cell_size: Real) -> NeighborGrid {
+        NeighborGrid {cell_size, cells: HashMap::new()}
+    }
+
+    fn cell(&self, point: &Vec2D) -> (i64, i64) {
+        ((point.x / self.cell_size).floor() as i64, (point.y / self.cell_size).floor() as i64)
+    }
+
+    pub fn rebuild(&mut self, points: &[Vec2D]) {
+        //Keep the allocated cells around since particles rarely move far between steps
+        for indices in self.cells.values_mut() {
+            indices.clear();
+        }
+        for (i, point) in points.iter().enumerate() {
+            let cell = self.cell(point);
+            self.cells.entry(cell).or_insert_with(Vec::new).push(i);
+        }
+        //Cells left behind are dropped so flowing liquid doesn't grow the map forever
+        self.cells.retain(|_, indices| !indices.is_empty());
+    }
+
+    //Pushes every index in the cells around point... candidates are at most two cells away so callers must check the distance
+    pub fn query(&self, point: &Vec2D, results: &mut Vec<usize>) {
+        let (x, y) = self.cell(point);
+        for dx in -1..2 {
+            for dy in -1..2 {
+                if let Some(indices) = self.cells.get(&(x + dx, y + dy)) {
+                    results.extend(indices.iter());
+                }
+            }
+        }
+    }
+}
+
+//Smoothing kernels in 2D (Muller et al. 2003) for smoothing radius h
+//Density
+pub fn poly6(r: Real, h: Real) -> Real {
+    if r >= h {
+        return 0.0;
+    }
+
+    let x = h * h - r * r;
+    4.0 / (PI as Real * h.powi(8)) * x * x * x
+}
+
+//Magnitude of the pressure gradient... points away from the neighbor
+pub fn spiky_gradient(r: Real, h: Real) -> Real {
+    if r >= h {
+        return 0.0;
+    }
+
+    -30.0 / (PI as Real * h.powi(5)) * (h - r) * (h - r)
+}
+
+//Laplacian of the viscosity kernel
+pub fn viscosity_laplacian(r: Real, h: Real) -> Real {
+    if r >= h {
+        return 0.0;
+    }
+
+    40.0 / (PI as Real * h.powi(5)) * (h - r)
+}
+
+//Liquid made of smoothed particle hydrodynamics (SPH) particles
+//Particle i is made of positions[i], velocities[i], densities[i] and pressures[i]
+#[derive(Clone)]
+pub struct Fluid {
+    pub positions: Vec<Vec2D>,
+    pub velocities: Vec<Vec2D>,
+    pub densities: Vec<Real>,
+    pub pressures: Vec<Real>,
+    pub particle_mass: Real,
+    pub rest_density: Real,
+    //Pressure generated per unit of density above the rest density
+    pub stiffness: Real,
+    pub viscosity: Real,
+    //Radius of influence of each particle
+    pub smoothing_radius: Real,
+    pub gravity: Vec2D,
+    //Radius of the particles when colliding with bodies in the world
+    pub particle_radius: Real,
+    pub restitution: Real,
+    pub substeps: usize,
+    pub color: [f32; 4],
+    grid: NeighborGrid
+}
+
+impl Fluid {
+    pub fn new(particle_mass: Real, rest_density: Real, smoothing_radius: Real) -> Fluid {
+        Fluid {
+            positions: Vec::new(),
+            velocities: Vec::new(),
+            densities: Vec::new(),
+            pressures: Vec::new(),
+            particle_mass,
+            rest_density,
+            stiffness: 200.0,
+            viscosity: 0.5,
+            smoothing_radius,
+            gravity: Vec2D::new(0.0, 0.0),
+            particle_radius: smoothing_radius * 0.25,
+            restitution: 0.0,
+            substeps: 4,
+            color: [0.1, 0.3, 0.9, 1.0],
+            grid: NeighborGrid::new(smoothing_radius)
+        }
+    }
+
+    //Fills a rectangle with particles spaced so the block starts near the rest density
+    pub fn create_block(min: Vec2D, max: Vec2D, spacing: Real, rest_density: Real) -> Fluid {
+        let particle_mass = rest_density * spacing * spacing;
+        let mut fluid = Fluid::new(particle_mass, rest_density, spacing * 2.0);
+
+        let columns = ((max.x - min.x) / spacing).floor() as usize;
+        let rows = ((max.y - min.y) / spacing).floor() as usize;
+        for row in 0..rows {
+            for col in 0..columns {
+                let offset = Vec2D::new((col as Real + 0.5) * spacing, (row as Real + 0.5) * spacing);
+                fluid.add_particle(min.add(&offset), Vec2D::new(0.0, 0.0));
+            }
+        }
+
+        fluid
+    }
+
+    pub fn add_particle(&mut self, position: Vec2D, velocity: Vec2D) {
+        self.positions.push(position);
+        self.velocities.push(velocity);
+        self.densities.push(self.rest_density);
+        self.pressures.push(0.0);
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        let extent = Vec2D::new(self.particle_radius, self.particle_radius);
+        let bounds = Aabb::from_points(&self.positions);
+        Aabb::new(bounds.min.sub(&extent), bounds.max.add(&extent))
+    }
+
+    fn compute_densities(&mut self, neighbors: &[Vec<usize>]) {
+        let h = self.smoothing_radius;
+        for i in 0..self.positions.len() {
+            let mut density = 0.0;
+            for &j in neighbors[i].iter() {
+                let r = self.positions[i].sub(&self.positions[j]).mag();
+                density += self.particle_mass * poly6(r, h);
+            }
+
+            self.densities[i] = density;
+            //Negative pressure would pull particles into clumps
+            self.pressures[i] = (self.stiffness * (density - self.rest_density)).max(0.0);
+        }
+    }
+
+    fn compute_accelerations(&self, neighbors: &[Vec<usize>]) -> Vec<Vec2D> {
+        let h = self.smoothing_radius;
+        let mut accelerations = vec![self.gravity; self.positions.len()];
+        for i in 0..self.positions.len() {
+            let mut force = Vec2D::new(0.0, 0.0);
+            for &j in neighbors[i].iter() {
+                if i == j {
+                    continue;
+                }
+
+                let offset = self.positions[i].sub(&self.positions[j]);
+                let r = offset.mag();
+                if r >= h {
+                    continue;
+                }
+
+                //Particles on top of each other are pushed apart in an arbitrary but consistent direction
+                let direction = offset.try_unit().unwrap_or(if i < j { Vec2D::new(0.0, -1.0) } else { Vec2D::new(0.0, 1.0) });
+
+                //Symmetric pressure force so pairs push each other equally
+                let pressure = (self.pressures[i] + self.pressures[j]) / (2.0 * self.densities[j]);
+                force = force.sub(&direction.mult(self.particle_mass * pressure * spiky_gradient(r, h)));
+
+                let relative_velocity = self.velocities[j].sub(&self.velocities[i]);
+                force = force.add(&relative_velocity.mult(self.viscosity * self.particle_mass / self.densities[j] * viscosity_laplacian(r, h)));
+            }
+
+            accelerations[i] = accelerations[i].add(&force.mult(1.0 / self.densities[i]));
+        }
+
+        accelerations
+    }
+
+    //Advances the fluid by timestep
+    //Particles collide with objects using their mass so floating and submerged bodies are pushed by the liquid
+    pub fn step(&mut self, timestep: Real, objects: &mut [Box<RenderableObject>]) {
+        let substeps = self.substeps.max(1);
+        let dt = timestep / substeps as Real;
+        let h = self.smoothing_radius;
+
+        for _ in 0..substeps {
+            self.grid.cell_size = h;
+            self.grid.rebuild(&self.positions);
+
+            let mut neighbors: Vec<Vec<usize>> = Vec::with_capacity(self.positions.len());
+            for i in 0..self.positions.len() {
+                let mut candidates = Vec::new();
+                self.grid.query(&self.positions[i], &mut candidates);
+                let position = self.positions[i];
+                candidates.retain(|&j| position.sub(&self.positions[j]).mag() < h);
+                neighbors.push(candidates);
+            }
+
+            self.compute_densities(&neighbors);
+            let accelerations = self.compute_accelerations(&neighbors);
+
+            for i in 0..self.positions.len() {
+                //Semi-implicit Euler
+                self.velocities[i] = self.velocities[i].add(&accelerations[i].mult(dt));
+                self.positions[i] = self.positions[i].add(&self.velocities[i].mult(dt));
+                contact::collide_point(&mut self.positions[i], &mut self.velocities[i], self.particle_mass,
+                                       self.particle_radius, self.restitution, objects);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Midpoint rule over rings of the disk of radius h
+    fn integrate<F: Fn(Real) -> Real>(h: Real, f: F) -> Real {
+        let steps = 10000;
+        let dr = h / steps as Real;
+        (0..steps).map(|i| f((i as Real + 0.5) * dr) * dr).sum()
+    }
+
+    #[test]
+    fn kernels_are_normalized() {
+        for &h in [0.5, 1.0, 2.0].iter() {
+            let density = integrate(h, |r| 2.0 * PI as Real * r * poly6(r, h));
+            assert!((density - 1.0).abs() < 1e-3);
+
+            //Integrating by parts turns the gradient back into the area integral of the spiky kernel
+            let spiky = integrate(h, |r| -PI as Real * r * r * spiky_gradient(r, h));
+            assert!((spiky - 1.0).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn kernels_vanish_outside_the_smoothing_radius() {
+        let h = 1.5;
+        for &r in [h, h + 0.1, 10.0].iter() {
+            assert_eq!(poly6(r, h), 0.0);
+            assert_eq!(spiky_gradient(r, h), 0.0);
+            assert_eq!(viscosity_laplacian(r, h), 0.0);
+        }
+
+        //Pressure always pushes apart and viscosity always smooths... both fade toward the edge
+        let mut previous = (spiky_gradient(0.0, h), viscosity_laplacian(0.0, h));
+        for i in 1..15 {
+            let r = i as Real * 0.1;
+            let current = (spiky_gradient(r, h), viscosity_laplacian(r, h));
+            assert!(current.0 < 0.0 && current.0 > previous.0);
+            assert!(current.1 > 0.0 && current.1 < previous.1);
+            previous = current;
+        }
+    }
+
+    #[test]
+    fn neighbor_grid_finds_every_close_pair() {
+        let points: Vec<Vec2D> = (0..200)
+            .map(|i| {
+                let i = i as Real;
+                Vec2D::new((i * 0.618).fract() * 10.0 - 5.0, (i * 0.382).fract() * 10.0 - 5.0)
+            })
+            .collect();
+        let mut grid = NeighborGrid::new(0.75);
+        grid.rebuild(&points);
+
+        let mut candidates = Vec::new();
+        for (i, point) in points.iter().enumerate() {
+            candidates.clear();
+            grid.query(point, &mut candidates);
+            for (j, other) in points.iter().enumerate() {
+                if point.sub(other).mag() < grid.cell_size {
+                    assert!(candidates.contains(&j), "{} and {} are neighbors", i, j);
+                }
+            }
+        }
+    }
+}
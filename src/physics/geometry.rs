@@ -0,0 +1,419 @@
+use physics::Vec2D;
+use physics::Real;
+
+use std::error::Error;
+use std::fmt;
+
+//Tolerance used to reject degenerate (zero area/zero length) geometry
+#[cfg(not(feature = "f32"))]
+pub const EPSILON: Real = 1e-9;
+#[cfg(feature = "f32")]
+pub const EPSILON: Real = 1e-5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PolygonError {
+    //Fewer than three distinct vertices were given
+    TooFewPoints,
+    //Polygon has (nearly) zero area or repeats a vertex
+    Degenerate,
+    //Two non-adjacent edges cross each other
+    SelfIntersecting,
+    //Polygon has a reflex corner where a convex one is required
+    NotConvex,
+}
+
+impl fmt::Display for PolygonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PolygonError::TooFewPoints => write!(f, "polygon needs at least three distinct points"),
+            PolygonError::Degenerate => write!(f, "polygon is degenerate (zero area or repeated points)"),
+            PolygonError::SelfIntersecting => write!(f, "polygon edges intersect each other"),
+            PolygonError::NotConvex => write!(f, "polygon is not convex (use Group::create_concave_polygon)"),
+        }
+    }
+}
+
+impl Error for PolygonError {}
+
+//z-component of the cross product (b - a) x (c - a)... positive when a, b, c turn counter-clockwise
+pub fn orientation(a: &Vec2D, b: &Vec2D, c: &Vec2D) -> Real {
+    let ab = b.sub(a);
+    let ac = c.sub(a);
+    ab.x * ac.y - ab.y * ac.x
+}
+
+//Shoelace formula... positive for counter-clockwise polygons
+pub fn signed_area(points: &[Vec2D]) -> Real {
+    let mut area = 0.0;
+    for i in 0..points.len() {
+        let p1 = &points[i];
+        let p2 = &points[(i + 1) % points.len()];
+        area += p1.x * p2.y - p2.x * p1.y;
+    }
+
+    area * 0.5
+}
+
+//Center of mass of a uniform polygon
+pub fn centroid(points: &[Vec2D]) -> Vec2D {
+    if points.is_empty() {
+        return Vec2D::new(0.0, 0.0);
+    }
+
+    let area = signed_area(points);
+    if area.abs() < EPSILON {
+        //Fall back to the average of the vertices for degenerate polygons
+        let mut sum = Vec2D::new(0.0, 0.0);
+        for p in points.iter() {
+            sum = sum.add(p);
+        }
+        return sum.mult(1.0 / points.len() as Real);
+    }
+
+    let mut cx = 0.0;
+    let mut cy = 0.0;
+    for i in 0..points.len() {
+        let p1 = &points[i];
+        let p2 = &points[(i + 1) % points.len()];
+        let cross = p1.x * p2.y - p2.x * p1.y;
+        cx += (p1.x + p2.x) * cross;
+        cy += (p1.y + p2.y) * cross;
+    }
+
+    Vec2D::new(cx / (6.0 * area), cy / (6.0 * area))
+}
+
+//Parameter t (0.0-1.0) of the point on the segment from a to b closest to p
+pub fn closest_parameter_on_segment(p: &Vec2D, a: &Vec2D, b: &Vec2D) -> Real {
+    let ab = b.sub(a);
+    let len_squared = ab.dot(&ab);
+    if len_squared < EPSILON {
+        return 0.0;
+    }
+
+    (p.sub(a).dot(&ab) / len_squared).max(0.0).min(1.0)
+}
+
+//Closest point to p lying on the segment from a to b
+pub fn closest_point_on_segment(p: &Vec2D, a: &Vec2D, b: &Vec2D) -> Vec2D {
+    let t = closest_parameter_on_segment(p, a, b);
+    a.add(&b.sub(a).mult(t))
+}
+
+//Returns whether segment a1-a2 and segment b1-b2 touch or cross
+pub fn segments_intersect(a1: &Vec2D, a2: &Vec2D, b1: &Vec2D, b2: &Vec2D) -> bool {
+    let d1 = orientation(b1, b2, a1);
+    let d2 = orientation(b1, b2, a2);
+    let d3 = orientation(a1, a2, b1);
+    let d4 = orientation(a1, a2, b2);
+
+    if ((d1 > EPSILON && d2 < -EPSILON) || (d1 < -EPSILON && d2 > EPSILON)) &&
+        ((d3 > EPSILON && d4 < -EPSILON) || (d3 < -EPSILON && d4 > EPSILON)) {
+        return true;
+    }
+
+    //Collinear cases... check if an end point lies on the other segment
+    (d1.abs() <= EPSILON && on_segment(a1, b1, b2)) ||
+        (d2.abs() <= EPSILON && on_segment(a2, b1, b2)) ||
+        (d3.abs() <= EPSILON && on_segment(b1, a1, a2)) ||
+        (d4.abs() <= EPSILON && on_segment(b2, a1, a2))
+}
+
+//Assumes p is collinear with a and b
+fn on_segment(p: &Vec2D, a: &Vec2D, b: &Vec2D) -> bool {
+    p.x <= a.x.max(b.x) + EPSILON && p.x >= a.x.min(b.x) - EPSILON &&
+        p.y <= a.y.max(b.y) + EPSILON && p.y >= a.y.min(b.y) - EPSILON
+}
+
+//Returns whether p is inside (or on the boundary of) the counter-clockwise triangle a, b, c
+fn point_in_triangle(p: &Vec2D, a: &Vec2D, b: &Vec2D, c: &Vec2D) -> bool {
+    orientation(a, b, p) >= -EPSILON && orientation(b, c, p) >= -EPSILON && orientation(c, a, p) >= -EPSILON
+}
+
+//Returns whether p is inside a counter-clockwise convex polygon
+pub fn point_in_convex(p: &Vec2D, points: &[Vec2D]) -> bool {
+    for i in 0..points.len() {
+        if orientation(&points[i], &points[(i + 1) % points.len()], p) < 0.0 {
+            return false;
+        }
+    }
+
+    true
+}
+
+//Part of polygon inside the counter-clockwise convex polygon clip (Sutherland-Hodgman)
+//polygon may be concave... the result is empty when they don't overlap
+pub fn clip_convex(polygon: &[Vec2D], clip: &[Vec2D]) -> Vec<Vec2D> {
+    let mut output: Vec<Vec2D> = polygon.to_vec();
+    for i in 0..clip.len() {
+        if output.is_empty() {
+            break;
+        }
+
+        let edge_start = &clip[i];
+        let edge_end = &clip[(i + 1) % clip.len()];
+        let input = output;
+        output = Vec::with_capacity(input.len() + 1);
+        for j in 0..input.len() {
+            let current = &input[j];
+            let next = &input[(j + 1) % input.len()];
+            let current_side = orientation(edge_start, edge_end, current);
+            let next_side = orientation(edge_start, edge_end, next);
+
+            if current_side >= 0.0 {
+                output.push(*current);
+            }
+            //Edge crosses the clip line so keep the crossing point
+            if (current_side >= 0.0) != (next_side >= 0.0) {
+                let t = current_side / (current_side - next_side);
+                output.push(current.lerp(next, t));
+            }
+        }
+    }
+
+    output
+}
+
+//Separating axis test between two convex point sets (a segment is a valid two point set)
+//Returns the axis of least penetration if the shapes overlap
+pub fn sat_axis(a: &[Vec2D], b: &[Vec2D]) -> Option<Vec2D> {
+    sat_contact(a, b).map(|(axis, _)| axis)
+}
+
+//Same as sat_axis but also returns the penetration depth along the axis
+pub fn sat_contact(a: &[Vec2D], b: &[Vec2D]) -> Option<(Vec2D, Real)> {
+    let mut best_axis: Option<Vec2D> = None;
+    let mut best_overlap = Real::INFINITY;
+
+    for shape in [a, b].iter() {
+        for i in 0..shape.len() {
+            let edge = shape[(i + 1) % shape.len()].sub(&shape[i]);
+            if edge.mag() < EPSILON {
+                continue;
+            }
+            let axis = edge.perp().unit();
+
+            let (min_a, max_a) = project(a, &axis);
+            let (min_b, max_b) = project(b, &axis);
+            if max_a <= min_b || max_b <= min_a {
+                //Found a separating axis
+                return None;
+            }
+            //Distance needed to push the shapes apart along the axis (works for zero width segments as well)
+            let overlap = (max_a - min_b).min(max_b - min_a);
+
+            if overlap < best_overlap {
+                best_overlap = overlap;
+                best_axis = Some(axis);
+            }
+        }
+    }
+
+    best_axis.map(|axis| (axis, best_overlap))
+}
+
+//Projects every point onto axis and returns the (min, max) interval
+fn project(points: &[Vec2D], axis: &Vec2D) -> (Real, Real) {
+    let mut min = Real::INFINITY;
+    let mut max = Real::NEG_INFINITY;
+    for p in points.iter() {
+        let d = p.dot(axis);
+        min = min.min(d);
+        max = max.max(d);
+    }
+
+    (min, max)
+}
+
+//Checks that points describe a simple polygon and returns it in counter-clockwise order
+//A closing point equal to the first point (as used by Group::create_polygon) is dropped
+pub fn validate_polygon(points: &[Vec2D]) -> Result<Vec<Vec2D>, PolygonError> {
+    let mut polygon: Vec<Vec2D> = points.to_vec();
+    if polygon.len() > 1 && polygon[0].sub(&polygon[polygon.len() - 1]).mag() < EPSILON {
+        polygon.pop();
+    }
+
+    if polygon.len() < 3 {
+        return Err(PolygonError::TooFewPoints);
+    }
+
+    //Repeated vertices would create zero length edges
+    for i in 0..polygon.len() {
+        for j in (i + 1)..polygon.len() {
+            if polygon[i].sub(&polygon[j]).mag() < EPSILON {
+                return Err(PolygonError::Degenerate);
+            }
+        }
+    }
+
+    //Every pair of non-adjacent edges must be disjoint
+    let n = polygon.len();
+    for i in 0..n {
+        for j in (i + 1)..n {
+            if j == i + 1 || (i == 0 && j == n - 1) {
+                continue;
+            }
+            if segments_intersect(&polygon[i], &polygon[(i + 1) % n], &polygon[j], &polygon[(j + 1) % n]) {
+                return Err(PolygonError::SelfIntersecting);
+            }
+        }
+    }
+
+    let area = signed_area(&polygon);
+    if area.abs() < EPSILON {
+        return Err(PolygonError::Degenerate);
+    }
+    if area < 0.0 {
+        polygon.reverse();
+    }
+
+    //Collinear vertices add nothing to the outline and would produce zero area triangles
+    let mut i = 0;
+    while i < polygon.len() && polygon.len() > 3 {
+        let n = polygon.len();
+        if orientation(&polygon[(i + n - 1) % n], &polygon[i], &polygon[(i + 1) % n]).abs() < EPSILON {
+            polygon.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+
+    Ok(polygon)
+}
+
+//Like validate_polygon but also rejects polygons with a reflex corner
+pub fn validate_convex(points: &[Vec2D]) -> Result<Vec<Vec2D>, PolygonError> {
+    let polygon = validate_polygon(points)?;
+
+    let n = polygon.len();
+    for i in 0..n {
+        if orientation(&polygon[(i + n - 1) % n], &polygon[i], &polygon[(i + 1) % n]) < 0.0 {
+            return Err(PolygonError::NotConvex);
+        }
+    }
+
+    Ok(polygon)
+}
+
+//Splits a simple polygon into counter-clockwise triangles using ear clipping
+pub fn triangulate(points: &[Vec2D]) -> Result<Vec<Vec<Vec2D>>, PolygonError> {
+    let polygon = validate_polygon(points)?;
+
+    let mut remaining: Vec<usize> = (0..polygon.len()).collect();
+    let mut triangles: Vec<Vec<Vec2D>> = Vec::new();
+
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let mut ear: Option<usize> = None;
+
+        for i in 0..n {
+            let prev = &polygon[remaining[(i + n - 1) % n]];
+            let current = &polygon[remaining[i]];
+            let next = &polygon[remaining[(i + 1) % n]];
+
+            //Reflex corners and ears thinner than validate_polygon accepts cannot be ears
+            if 0.5 * orientation(prev, current, next) < EPSILON {
+                continue;
+            }
+
+            //No other vertex may lie inside the candidate ear
+            let mut contains_vertex = false;
+            for j in 0..n {
+                if j == i || j == (i + n - 1) % n || j == (i + 1) % n {
+                    continue;
+                }
+                if point_in_triangle(&polygon[remaining[j]], prev, current, next) {
+                    contains_vertex = true;
+                    break;
+                }
+            }
+
+            if !contains_vertex {
+                ear = Some(i);
+                break;
+            }
+        }
+
+        match ear {
+            Some(i) => {
+                triangles.push(vec![
+                    polygon[remaining[(i + n - 1) % n]],
+                    polygon[remaining[i]],
+                    polygon[remaining[(i + 1) % n]]
+                ]);
+                remaining.remove(i);
+            },
+            //Every simple polygon has an ear... not finding one means the input is numerically degenerate
+            None => return Err(PolygonError::Degenerate)
+        }
+    }
+
+    let last: Vec<Vec2D> = remaining.iter().map(|&i| polygon[i]).collect();
+    if signed_area(&last) < EPSILON {
+        return Err(PolygonError::Degenerate);
+    }
+    triangles.push(last);
+
+    Ok(triangles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concave_polygon_is_triangulated() {
+        let l_shape = vec![
+            Vec2D::new(0.0, 0.0),
+            Vec2D::new(2.0, 0.0),
+            Vec2D::new(2.0, 1.0),
+            Vec2D::new(1.0, 1.0),
+            Vec2D::new(1.0, 2.0),
+            Vec2D::new(0.0, 2.0)
+        ];
+        assert_eq!(validate_convex(&l_shape), Err(PolygonError::NotConvex));
+
+        let triangles = triangulate(&l_shape).unwrap();
+        assert_eq!(triangles.len(), 4);
+        let area: Real = triangles.iter().map(|triangle| signed_area(triangle)).sum();
+        assert!((area - 3.0).abs() < EPSILON);
+        //Every piece has to be accepted as a convex polygon of its own
+        for triangle in triangles.iter() {
+            assert!(validate_convex(triangle).is_ok());
+        }
+    }
+
+    #[test]
+    fn thin_ears_are_not_clipped() {
+        //The first corner bulges out just enough to be kept but cutting it off would leave a sliver
+        let points = vec![
+            Vec2D::new(1.0, -0.75 * EPSILON),
+            Vec2D::new(2.0, 0.0),
+            Vec2D::new(2.0, 1.0),
+            Vec2D::new(0.0, 1.0),
+            Vec2D::new(0.0, 0.0)
+        ];
+        for triangle in triangulate(&points).unwrap().iter() {
+            assert!(signed_area(triangle) >= EPSILON);
+        }
+    }
+
+    #[test]
+    fn collinear_points_are_degenerate() {
+        let points = vec![Vec2D::new(0.0, 0.0), Vec2D::new(1.0, 0.0), Vec2D::new(2.0, 0.0)];
+        assert_eq!(validate_polygon(&points), Err(PolygonError::Degenerate));
+        assert_eq!(triangulate(&points), Err(PolygonError::Degenerate));
+    }
+
+    #[test]
+    fn self_intersecting_polygon_is_rejected() {
+        let bow_tie = vec![
+            Vec2D::new(0.0, 0.0),
+            Vec2D::new(1.0, 1.0),
+            Vec2D::new(1.0, 0.0),
+            Vec2D::new(0.0, 1.0)
+        ];
+        assert_eq!(validate_polygon(&bow_tie), Err(PolygonError::SelfIntersecting));
+        assert_eq!(triangulate(&bow_tie), Err(PolygonError::SelfIntersecting));
+    }
+}
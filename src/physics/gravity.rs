@@ -0,0 +1,312 @@
+use physics::Vec2D;
+use physics::Real;
+use physics::bounds::Aabb;
+use physics::shapes::*;
+
+use renderer::RenderableObject;
+
+//Cells stop splitting at this depth so bodies at the same position can't recurse forever
+const MAX_DEPTH: usize = 32;
+
+struct QuadNode {
+    //Square cell covered by the node
+    center: Vec2D,
+    half_size: Real,
+    mass: Real,
+    //Center of mass of every body in the cell
+    com: Vec2D,
+    //Indices of the four child nodes... None for leaves
+    children: Option<[usize; 4]>,
+    //Bodies stored in a leaf
+    bodies: Vec<usize>
+}
+
+//Quadtree over point masses used for the Barnes-Hut approximation
+pub struct QuadTree {
+    nodes: Vec<QuadNode>
+}
+
+impl QuadTree {
+    pub fn new(positions: &[Vec2D], masses: &[Real]) -> QuadTree {
+        let mut tree = QuadTree {nodes: Vec::new()};
+        if positions.is_empty() {
+            return tree;
+        }
+
+        let bounds = Aabb::from_points(positions);
+        let center = bounds.min.add(&bounds.max).mult(0.5);
+        let half_size = (bounds.max.x - bounds.min.x).max(bounds.max.y - bounds.min.y) * 0.5 + 1e-6;
+        tree.nodes.push(QuadNode {
+            center,
+            half_size,
+            mass: 0.0,
+            com: Vec2D::new(0.0, 0.0),
+            children: None,
+            bodies: (0..positions.len()).collect()
+        });
+        tree.split(0, positions, 0);
+        tree.summarize(0, positions, masses);
+
+        tree
+    }
+
+    fn quadrant(center: &Vec2D, point: &Vec2D) -> usize {
+        (if point.x >= center.x { 1 } else { 0 }) + (if point.y >= center.y { 2 } else { 0 })
+    }
+
+    fn split(&mut self, index: usize, positions: &[Vec2D], depth: usize) {
+        if self.nodes[index].bodies.len() <= 1 || depth >= MAX_DEPTH {
+            return;
+        }
+
+        let center = self.nodes[index].center;
+        let quarter = self.nodes[index].half_size * 0.5;
+        let bodies = ::std::mem::replace(&mut self.nodes[index].bodies, Vec::new());
+
+        let mut children = [0; 4];
+        for quadrant in 0..4 {
+            let offset = Vec2D::new(
+                if quadrant & 1 == 1 { quarter } else { -quarter },
+                if quadrant & 2 == 2 { quarter } else { -quarter }
+            );
+            children[quadrant] = self.nodes.len();
+            self.nodes.push(QuadNode {
+                center: center.add(&offset),
+                half_size: quarter,
+                mass: 0.0,
+                com: Vec2D::new(0.0, 0.0),
+                children: None,
+                bodies: Vec::new()
+            });
+        }
+        for body in bodies {
+            let quadrant = QuadTree::quadrant(&center, &positions[body]);
+            self.nodes[children[quadrant]].bodies.push(body);
+        }
+        self.nodes[index].children = Some(children);
+
+        for &child in children.iter() {
+            self.split(child, positions, depth + 1);
+        }
+    }
+
+    //Fills in the mass and center of mass of every node bottom up
+    fn summarize(&mut self, index: usize, positions: &[Vec2D], masses: &[Real]) {
+        let mut mass = 0.0;
+        let mut weighted = Vec2D::new(0.0, 0.0);
+        match self.nodes[index].children {
+            Some(children) => {
+                for &child in children.iter() {
+                    self.summarize(child, positions, masses);
+                    mass += self.nodes[child].mass;
+                    weighted = weighted.add(&self.nodes[child].com.mult(self.nodes[child].mass));
+                }
+            },
+            None => {
+                for &body in self.nodes[index].bodies.iter() {
+                    mass += masses[body];
+                    weighted = weighted.add(&positions[body].mult(masses[body]));
+                }
+            }
+        }
+
+        self.nodes[index].mass = mass;
+        self.nodes[index].com = if mass > 0.0 { weighted.mult(1.0 / mass) } else { self.nodes[index].center };
+    }
+
+    //Acceleration at body from every other body
+    //Cells smaller than theta times their distance are treated as a single mass
+    pub fn acceleration(&self, body: usize, positions: &[Vec2D], masses: &[Real], g: Real, softening: Real, theta: Real) -> Vec2D {
+        let mut acceleration = Vec2D::new(0.0, 0.0);
+        if self.nodes.is_empty() {
+            return acceleration;
+        }
+
+        let position = positions[body];
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if node.mass <= 0.0 {
+                continue;
+            }
+
+            match node.children {
+                Some(children) => {
+                    let distance = node.com.sub(&position).mag();
+                    if distance > 0.0 && node.half_size * 2.0 < theta * distance {
+                        acceleration = acceleration.add(&pull(&position, &node.com, node.mass, g, softening));
+                    } else {
+                        stack.extend(children.iter());
+                    }
+                },
+                None => {
+                    for &other in node.bodies.iter() {
+                        if other != body {
+                            acceleration = acceleration.add(&pull(&position, &positions[other], masses[other], g, softening));
+                        }
+                    }
+                }
+            }
+        }
+
+        acceleration
+    }
+}
+
+//Softened inverse-square acceleration at position towards a mass at source
+fn pull(position: &Vec2D, source: &Vec2D, mass: Real, g: Real, softening: Real) -> Vec2D {
+    let offset = source.sub(position);
+    let distance_squared = offset.dot(&offset) + softening * softening;
+    if distance_squared <= 0.0 {
+        return Vec2D::new(0.0, 0.0);
+    }
+
+    offset.mult(g * mass / (distance_squared * distance_squared.sqrt()))
+}
+
+//Walls and terrain (lines, chains and heightfields) don't attract anything
+fn gravitating_mass(body: &RenderableObject) -> Real {
+    if body.as_any().is::<Line>() || body.as_any().is::<Chain>() || body.as_any().is::<Heightfield>() {
+        return 0.0;
+    }
+
+    body.get_mass().max(0.0)
+}
+
+//Newtonian attraction between every pair of bodies
+#[derive(Clone)]
+pub struct MutualGravity {
+    //Gravitational constant
+    pub g: Real,
+    //Added to distances so close encounters don't produce huge accelerations
+    pub softening: Real,
+    //Opening angle of the Barnes-Hut approximation... 0.0 is exact and larger values are faster but less accurate
+    pub theta: Real,
+    //Body count from which the Barnes-Hut quadtree is used instead of summing every pair
+    pub threshold: usize
+}
+
+impl MutualGravity {
+    pub fn new(g: Real) -> MutualGravity {
+        MutualGravity {
+            g,
+            softening: 0.1,
+            theta: 0.5,
+            threshold: 64
+        }
+    }
+
+    //Sums the pull of every pair... O(n^2)
+    pub fn accelerations_exact(&self, positions: &[Vec2D], masses: &[Real]) -> Vec<Vec2D> {
+        let mut accelerations = vec![Vec2D::new(0.0, 0.0); positions.len()];
+        for i in 0..positions.len() {
+            for j in (i + 1)..positions.len() {
+                let on_i = pull(&positions[i], &positions[j], 1.0, self.g, self.softening);
+                accelerations[i] = accelerations[i].add(&on_i.mult(masses[j]));
+                accelerations[j] = accelerations[j].sub(&on_i.mult(masses[i]));
+            }
+        }
+
+        accelerations
+    }
+
+    //Approximates distant groups of bodies by their center of mass... O(n log n)
+    pub fn accelerations_barnes_hut(&self, positions: &[Vec2D], masses: &[Real]) -> Vec<Vec2D> {
+        let tree = QuadTree::new(positions, masses);
+        (0..positions.len())
+            .map(|i| tree.acceleration(i, positions, masses, self.g, self.softening, self.theta))
+            .collect()
+    }
+
+    pub fn accelerations(&self, positions: &[Vec2D], masses: &[Real]) -> Vec<Vec2D> {
+        if positions.len() >= self.threshold {
+            self.accelerations_barnes_hut(positions, masses)
+        } else {
+            self.accelerations_exact(positions, masses)
+        }
+    }
+
+    //Softened potential energy of every pair... matches the force used by accelerations
+    pub fn potential_energy(&self, objects: &[Box<RenderableObject>]) -> Real {
+        let positions: Vec<Vec2D> = objects.iter().map(|obj| obj.get_com()).collect();
+        let masses: Vec<Real> = objects.iter().map(|obj| gravitating_mass(&**obj)).collect();
+
+        let mut energy = 0.0;
+        for i in 0..positions.len() {
+            for j in (i + 1)..positions.len() {
+                let offset = positions[i].sub(&positions[j]);
+                let distance = (offset.dot(&offset) + self.softening * self.softening).sqrt();
+                if distance > 0.0 {
+                    energy -= self.g * masses[i] * masses[j] / distance;
+                }
+            }
+        }
+
+        energy
+    }
+
+    //Static bodies attract others but don't move... massless bodies are pulled without pulling back
+    pub fn apply(&self, timestep: Real, objects: &mut [Box<RenderableObject>]) {
+        let positions: Vec<Vec2D> = objects.iter().map(|obj| obj.get_com()).collect();
+        let masses: Vec<Real> = objects.iter().map(|obj| gravitating_mass(&**obj)).collect();
+        let accelerations = self.accelerations(&positions, &masses);
+
+        for (obj, acceleration) in objects.iter_mut().zip(accelerations.iter()) {
+            if obj.get_static() {
+                continue;
+            }
+
+            let velocity = obj.get_velocity();
+            obj.set_velocity(&velocity.add(&acceleration.mult(timestep)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Bodies scattered over a disk with uneven masses... enough of them to use the quadtree
+    fn bodies(count: usize) -> (Vec<Vec2D>, Vec<Real>) {
+        let positions = (0..count)
+            .map(|i| {
+                let radius = 50.0 * ((i as Real + 0.5) / count as Real).sqrt();
+                let angle = i as Real * 2.399963;
+                Vec2D::new(radius * angle.cos(), radius * angle.sin())
+            })
+            .collect();
+        let masses = (0..count).map(|i| 1.0 + (i % 7) as Real).collect();
+        (positions, masses)
+    }
+
+    //Largest error of any body relative to the mean magnitude of the exact accelerations
+    //Bodies near the middle feel almost no net pull so their own magnitude is a poor scale
+    fn worst_error(gravity: &MutualGravity, positions: &[Vec2D], masses: &[Real]) -> Real {
+        let exact = gravity.accelerations_exact(positions, masses);
+        let approximate = gravity.accelerations(positions, masses);
+        let scale = exact.iter().map(|acceleration| acceleration.mag()).sum::<Real>() / exact.len() as Real;
+        exact.iter().zip(approximate.iter())
+            .map(|(exact, approximate)| approximate.sub(exact).mag() / scale)
+            .fold(0.0, |worst: Real, error| worst.max(error))
+    }
+
+    #[test]
+    fn barnes_hut_matches_exact_sum() {
+        let mut gravity = MutualGravity::new(1.0);
+        let (positions, masses) = bodies(4 * gravity.threshold);
+        assert!(positions.len() >= gravity.threshold);
+
+        let mut previous = worst_error(&gravity, &positions, &masses);
+        assert!(previous < 0.08);
+
+        //Smaller opening angles open more cells so the error shrinks toward zero
+        for &theta in [0.25, 0.1, 0.0].iter() {
+            gravity.theta = theta;
+            let error = worst_error(&gravity, &positions, &masses);
+            assert!(error < previous);
+            previous = error;
+        }
+        //Only rounding differences remain once every cell is opened
+        assert!(previous < 1e-4);
+    }
+}
@@ -1,27 +1,75 @@
 use renderer::RenderableObject;
 
 pub mod shapes;
-use physics::shapes::*;
+pub mod geometry;
+pub mod bounds;
+pub mod tilemap;
+pub mod transform;
+pub mod fixed;
+pub mod contact;
+pub mod softbody;
+pub mod verlet;
+pub mod particles;
+pub mod fluid;
+pub mod buoyancy;
+pub mod fields;
+pub mod gravity;
+pub mod electromagnetism;
+pub mod molecular;
+pub mod thermo;
+pub mod diagnostics;
+pub mod binary;
+#[cfg(feature = "serialize")]
+pub mod scene;
+
+use physics::bounds::Aabb;
+use physics::bounds::OutOfBounds;
+use physics::verlet::AnchorTarget;
+use physics::softbody::SoftBody;
+use physics::verlet::VerletBody;
+use physics::particles::ParticleSystem;
+use physics::fluid::Fluid;
+use physics::buoyancy::WaterVolume;
+use physics::fields::ForceField;
+use physics::gravity::MutualGravity;
+use physics::electromagnetism::Electromagnetism;
+use physics::molecular::MolecularDynamics;
+use physics::thermo::ThermoAnalysis;
+use physics::diagnostics::ConservationMonitor;
+#[cfg(feature = "fixed")]
+use physics::fixed::Fixed;
 
 use std::any::Any;
+use std::ops;
+use std::fmt;
+use std::error::Error;
 
 //Trait marker signifying object for simulation
 pub trait Object {
     fn get_com(&self) -> Vec2D;
     fn set_com(&mut self, com: &Vec2D);
 
-    fn get_mass(&self) -> f64;
-    fn set_mass(&mut self, mass: f64);
+    fn get_mass(&self) -> Real;
+    fn set_mass(&mut self, mass: Real);
 
     fn get_velocity(&self) -> Vec2D;
     fn set_velocity(&mut self, velocity: &Vec2D);
 
-    fn get_friction(&self) -> f64;
-    fn set_friction(&mut self, friction_k: f64);
+    fn get_friction(&self) -> Real;
+    fn set_friction(&mut self, friction_k: Real);
 
     fn get_static(&self) -> bool;
     fn set_static(&mut self, is_static: bool);
 
+    fn get_category(&self) -> u32;
+    fn set_category(&mut self, category: u32);
+
+    fn get_charge(&self) -> Real;
+    fn set_charge(&mut self, charge: Real);
+
+    fn get_out_of_bounds(&self) -> OutOfBounds;
+    fn set_out_of_bounds(&mut self, policy: OutOfBounds);
+
     fn as_any(&self) -> &Any;
 }
 
@@ -31,26 +79,114 @@ pub trait Collidable {
 
     //Returns vector describing direction of collision(self on other)
     fn collision_direction(&self, other: &RenderableObject) -> Option<Vec2D>;
+
+    //Returns box enclosing the whole body
+    fn bounds(&self) -> Aabb;
 }
 
-#[derive(Debug)]
+//Floating point type used for all physics state... enable the "f32" feature for single precision
+#[cfg(not(feature = "f32"))]
+pub type Real = f64;
+#[cfg(feature = "f32")]
+pub type Real = f32;
+
+//Fixed point values are stored in Real so they need the full double precision mantissa
+#[cfg(all(feature = "f32", feature = "fixed"))]
+compile_error!("the \"fixed\" feature cannot be combined with the \"f32\" feature");
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Vec2D {
-    pub x: f64,
-    pub y: f64
+    pub x: Real,
+    pub y: Real
+}
+
+//First non-finite property found by World::validate
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidBodyState {
+    //World field holding the body (ex. "objects", "fluids")
+    pub collection: &'static str,
+    //Index of the body in that field
+    pub index: usize,
+    //Point or particle of the body that is invalid... None for rigid bodies in World::objects
+    pub element: Option<usize>,
+    pub property: &'static str
+}
+
+impl fmt::Display for InvalidBodyState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.element {
+            Some(element) => write!(f, "{}[{}] point {} has a non-finite {}", self.collection, self.index, element, self.property),
+            None => write!(f, "{}[{}] has a non-finite {}", self.collection, self.index, self.property)
+        }
+    }
+}
+
+impl Error for InvalidBodyState {}
+
+//Body that left World::bounds with the OutOfBounds::Event policy
+pub struct OutOfBoundsEvent {
+    //Position of the body in World::objects at the start of the step
+    pub index: usize,
+    pub body: Box<RenderableObject>
+}
+
+//Copy of everything World::update reads or changes
+//Observers (thermo, diagnostics) and pending out of bounds events are not part of the state and are left alone by restore
+#[derive(Clone)]
+pub struct WorldSnapshot {
+    pub gravity: Real,
+    pub timestep: Real,
+    pub objects: Vec<Box<RenderableObject>>,
+    pub soft_bodies: Vec<SoftBody>,
+    pub verlet_bodies: Vec<VerletBody>,
+    pub particle_systems: Vec<ParticleSystem>,
+    pub fluids: Vec<Fluid>,
+    pub water_volumes: Vec<WaterVolume>,
+    pub force_fields: Vec<ForceField>,
+    pub mutual_gravity: Option<MutualGravity>,
+    pub electromagnetism: Option<Electromagnetism>,
+    pub molecular_dynamics: Option<MolecularDynamics>,
+    pub periodic: Option<Aabb>,
+    pub bounds: Option<Aabb>
 }
 
 pub struct World {
-    pub gravity: f64,
+    pub gravity: Real,
     pub objects: Vec<Box<RenderableObject>>,
-    pub timestep: f64,
+    pub timestep: Real,
+    pub soft_bodies: Vec<SoftBody>,
+    pub verlet_bodies: Vec<VerletBody>,
+    pub particle_systems: Vec<ParticleSystem>,
+    pub fluids: Vec<Fluid>,
+    pub water_volumes: Vec<WaterVolume>,
+    pub force_fields: Vec<ForceField>,
+    //Bodies attract each other when set
+    pub mutual_gravity: Option<MutualGravity>,
+    //Charged bodies interact when set
+    pub electromagnetism: Option<Electromagnetism>,
+    //Circles interact through a Lennard-Jones potential when set
+    pub molecular_dynamics: Option<MolecularDynamics>,
+    //Samples temperature, wall pressure and speed distribution every step when set
+    pub thermo: Option<ThermoAnalysis>,
+    //Tracks energy and momentum drift every step when set
+    pub diagnostics: Option<ConservationMonitor>,
+    //Bodies leaving one side of this box come back on the other and collide across the seam when set
+    pub periodic: Option<Aabb>,
+    //Bodies whose center of mass leaves this box are handled by their OutOfBounds policy when set
+    pub bounds: Option<Aabb>,
+    //Bodies removed with the OutOfBounds::Event policy... drained by the caller
+    pub out_of_bounds_events: Vec<OutOfBoundsEvent>,
 }
 
+//Floating point arithmetic backend
+#[cfg(not(feature = "fixed"))]
 impl Vec2D {
-    pub fn new(x: f64, y: f64) -> Self {
+    pub fn new(x: Real, y: Real) -> Self {
         Vec2D{x, y}
     }
 
-    pub fn mag(&self) -> f64 {
+    pub fn mag(&self) -> Real {
         (self.x.powi(2) + self.y.powi(2)).powf(0.5)
     }
 
@@ -62,56 +198,552 @@ impl Vec2D {
         Vec2D::new(self.x - other.x, self.y - other.y)
     }
 
-    pub fn mult(&self, scalar: f64) -> Vec2D {
+    pub fn mult(&self, scalar: Real) -> Vec2D {
         Vec2D::new(self.x * scalar, self.y * scalar)
     }
 
-    pub fn dot(&self, other: &Vec2D) -> f64{
+    pub fn dot(&self, other: &Vec2D) -> Real{
         self.x * other.x + self.y * other.y
     }
 
+    //z-component of the 3D cross product
+    pub fn cross(&self, other: &Vec2D) -> Real {
+        self.x * other.y - self.y * other.x
+    }
+}
+
+//Fixed point arithmetic backend
+//Vec2D operations are rounded to the Q32.32 grid and done with integers (mag uses an integer square root instead of powf)
+//Components below fixed::MAX_EXACT in magnitude stay exactly on the grid... larger ones are rounded by f64
+//This is not a deterministic mode... components are stored as f64 and every scalar in the solver (masses, impulse
+//ratios, friction, unit, rotate, fluid kernels, thermostats) is still floating point, so results can differ between machines
+#[cfg(feature = "fixed")]
+impl Vec2D {
+    pub fn new(x: Real, y: Real) -> Self {
+        Vec2D{x: Fixed::from_real(x).to_real(), y: Fixed::from_real(y).to_real()}
+    }
+
+    fn to_fixed(&self) -> (Fixed, Fixed) {
+        (Fixed::from_real(self.x), Fixed::from_real(self.y))
+    }
+
+    fn from_fixed(x: Fixed, y: Fixed) -> Vec2D {
+        Vec2D{x: x.to_real(), y: y.to_real()}
+    }
+
+    pub fn mag(&self) -> Real {
+        let (x, y) = self.to_fixed();
+        (x * x + y * y).sqrt().to_real()
+    }
+
+    pub fn add(&self, other: &Vec2D) -> Vec2D {
+        let ((x1, y1), (x2, y2)) = (self.to_fixed(), other.to_fixed());
+        Vec2D::from_fixed(x1 + x2, y1 + y2)
+    }
+
+    pub fn sub(&self, other: &Vec2D) -> Vec2D {
+        let ((x1, y1), (x2, y2)) = (self.to_fixed(), other.to_fixed());
+        Vec2D::from_fixed(x1 - x2, y1 - y2)
+    }
+
+    pub fn mult(&self, scalar: Real) -> Vec2D {
+        let (x, y) = self.to_fixed();
+        let scalar = Fixed::from_real(scalar);
+        Vec2D::from_fixed(x * scalar, y * scalar)
+    }
+
+    pub fn dot(&self, other: &Vec2D) -> Real{
+        let ((x1, y1), (x2, y2)) = (self.to_fixed(), other.to_fixed());
+        (x1 * x2 + y1 * y2).to_real()
+    }
+
+    //z-component of the 3D cross product
+    pub fn cross(&self, other: &Vec2D) -> Real {
+        let ((x1, y1), (x2, y2)) = (self.to_fixed(), other.to_fixed());
+        (x1 * y2 - y1 * x2).to_real()
+    }
+}
+
+impl Vec2D {
+    //Does not check for zero length vectors... use try_unit or normalize_or_zero when the length can be zero
     pub fn unit(&self) -> Vec2D {
         let mag = self.mag();
         Vec2D::new(self.x / mag, self.y / mag)
     }
 
+    //Returns None for zero length or non-finite vectors
+    pub fn try_unit(&self) -> Option<Vec2D> {
+        let mag = self.mag();
+        if mag > 0.0 && mag.is_finite() {
+            Some(Vec2D::new(self.x / mag, self.y / mag))
+        } else {
+            None
+        }
+    }
+
+    //Projecting onto a zero length(degenerate) axis gives the zero vector
     pub fn proj_on(&self, other: &Vec2D) -> Vec2D {
-        other.unit().mult(self.dot(other) / other.mag())
+        match other.try_unit() {
+            Some(axis) => axis.mult(self.dot(&axis)),
+            None => Vec2D::new(0.0, 0.0)
+        }
     }
 
     pub fn reject_on(&self, other: &Vec2D) -> Vec2D {
         self.sub(&self.proj_on(&other))
     }
 
+    pub fn is_finite(&self) -> bool {
+        self.x.is_finite() && self.y.is_finite()
+    }
+
     pub fn perp(&self) -> Vec2D {
         Vec2D::new(-1.0 * self.y, self.x)
     }
+
+    //Unit vector in the same direction or the zero vector if this vector has no length
+    pub fn normalize_or_zero(&self) -> Vec2D {
+        let mag = self.mag();
+        if mag > 0.0 {
+            Vec2D::new(self.x / mag, self.y / mag)
+        } else {
+            Vec2D::new(0.0, 0.0)
+        }
+    }
+
+    //Rotates counter-clockwise by angle(radians)
+    pub fn rotate(&self, angle: Real) -> Vec2D {
+        let (sin, cos) = angle.sin_cos();
+        Vec2D::new(self.x * cos - self.y * sin, self.x * sin + self.y * cos)
+    }
+
+    //Linear interpolation... t = 0.0 gives self and t = 1.0 gives other
+    pub fn lerp(&self, other: &Vec2D, t: Real) -> Vec2D {
+        Vec2D::new(self.x + (other.x - self.x) * t, self.y + (other.y - self.y) * t)
+    }
+
+    pub fn distance(&self, other: &Vec2D) -> Real {
+        self.sub(other).mag()
+    }
+}
+
+impl ops::Add for Vec2D {
+    type Output = Vec2D;
+
+    fn add(self, other: Vec2D) -> Vec2D {
+        Vec2D::add(&self, &other)
+    }
 }
 
-impl Clone for Vec2D {
-    fn clone(&self) -> Vec2D {
-        Vec2D::new(self.x, self.y)
+impl ops::Sub for Vec2D {
+    type Output = Vec2D;
+
+    fn sub(self, other: Vec2D) -> Vec2D {
+        Vec2D::sub(&self, &other)
     }
+}
+
+impl ops::Mul<Real> for Vec2D {
+    type Output = Vec2D;
+
+    fn mul(self, scalar: Real) -> Vec2D {
+        self.mult(scalar)
+    }
+}
+
+impl ops::Mul<Vec2D> for Real {
+    type Output = Vec2D;
+
+    fn mul(self, vector: Vec2D) -> Vec2D {
+        vector.mult(self)
+    }
+}
+
+impl ops::Neg for Vec2D {
+    type Output = Vec2D;
+
+    fn neg(self) -> Vec2D {
+        self.mult(-1.0)
+    }
+}
+
+impl ops::AddAssign for Vec2D {
+    fn add_assign(&mut self, other: Vec2D) {
+        *self = Vec2D::add(self, &other);
+    }
+}
 
-    fn clone_from(&mut self, source: &Vec2D) {
-        self.x = source.x;
-        self.y = source.y;
+impl ops::SubAssign for Vec2D {
+    fn sub_assign(&mut self, other: Vec2D) {
+        *self = Vec2D::sub(self, &other);
     }
 }
 
 impl World {
-    pub fn new(gravity: f64, timestep: f64) -> World {
-        World {gravity, objects: Vec::new(), timestep}
+    pub fn new(gravity: Real, timestep: Real) -> World {
+        World {
+            gravity,
+            objects: Vec::new(),
+            timestep,
+            soft_bodies: Vec::new(),
+            verlet_bodies: Vec::new(),
+            particle_systems: Vec::new(),
+            fluids: Vec::new(),
+            water_volumes: Vec::new(),
+            force_fields: Vec::new(),
+            mutual_gravity: None,
+            electromagnetism: None,
+            molecular_dynamics: None,
+            thermo: None,
+            diagnostics: None,
+            periodic: None,
+            bounds: None,
+            out_of_bounds_events: Vec::new()
+        }
     }
 
     pub fn add_object<T: RenderableObject + 'static>(&mut self, object: T) {
         self.objects.push(Box::new(object));
     }
 
+    //Deep copy of the simulation state... restoring it and stepping again gives the same results
+    pub fn snapshot(&self) -> WorldSnapshot {
+        WorldSnapshot {
+            gravity: self.gravity,
+            timestep: self.timestep,
+            objects: self.objects.clone(),
+            soft_bodies: self.soft_bodies.clone(),
+            verlet_bodies: self.verlet_bodies.clone(),
+            particle_systems: self.particle_systems.clone(),
+            fluids: self.fluids.clone(),
+            water_volumes: self.water_volumes.clone(),
+            force_fields: self.force_fields.clone(),
+            mutual_gravity: self.mutual_gravity.clone(),
+            electromagnetism: self.electromagnetism.clone(),
+            molecular_dynamics: self.molecular_dynamics.clone(),
+            periodic: self.periodic,
+            bounds: self.bounds
+        }
+    }
+
+    //The snapshot is copied so it can be restored again (ex. repeated what-if runs)
+    pub fn restore(&mut self, snapshot: &WorldSnapshot) {
+        let snapshot = snapshot.clone();
+        self.gravity = snapshot.gravity;
+        self.timestep = snapshot.timestep;
+        self.objects = snapshot.objects;
+        self.soft_bodies = snapshot.soft_bodies;
+        self.verlet_bodies = snapshot.verlet_bodies;
+        self.particle_systems = snapshot.particle_systems;
+        self.fluids = snapshot.fluids;
+        self.water_volumes = snapshot.water_volumes;
+        self.force_fields = snapshot.force_fields;
+        self.mutual_gravity = snapshot.mutual_gravity;
+        self.electromagnetism = snapshot.electromagnetism;
+        self.molecular_dynamics = snapshot.molecular_dynamics;
+        self.periodic = snapshot.periodic;
+        self.bounds = snapshot.bounds;
+    }
+
+    //Removes objects[index] keeping the order of the others
+    //Verlet anchors to the body are dropped and anchors to later bodies are renumbered
+    pub fn remove_object(&mut self, index: usize) -> Box<RenderableObject> {
+        for body in self.verlet_bodies.iter_mut() {
+            body.anchors.retain(|anchor| match anchor.target {
+                AnchorTarget::Body(target, _) => target != index,
+                AnchorTarget::World(_) => true
+            });
+            for anchor in body.anchors.iter_mut() {
+                if let AnchorTarget::Body(ref mut target, _) = anchor.target {
+                    if *target > index {
+                        *target -= 1;
+                    }
+                }
+            }
+        }
+
+        self.objects.remove(index)
+    }
+
+    //Applies the OutOfBounds policy of every non-static body outside bounds
+    fn enforce_bounds(&mut self) {
+        let bounds = match self.bounds {
+            Some(bounds) => bounds,
+            None => return
+        };
+
+        //Walk backwards so removals don't shift bodies that are still to be checked
+        let mut events = Vec::new();
+        for index in (0..self.objects.len()).rev() {
+            if self.objects[index].get_static() {
+                continue;
+            }
+
+            let com = self.objects[index].get_com();
+            if bounds.contains_point(&com) {
+                continue;
+            }
+
+            match self.objects[index].get_out_of_bounds() {
+                OutOfBounds::Despawn => {
+                    self.remove_object(index);
+                },
+                OutOfBounds::Clamp => {
+                    let clamped = Vec2D::new(com.x.max(bounds.min.x).min(bounds.max.x), com.y.max(bounds.min.y).min(bounds.max.y));
+                    let velocity = self.objects[index].get_velocity();
+                    let velocity = Vec2D::new(
+                        if (com.x < bounds.min.x && velocity.x < 0.0) || (com.x > bounds.max.x && velocity.x > 0.0) { 0.0 } else { velocity.x },
+                        if (com.y < bounds.min.y && velocity.y < 0.0) || (com.y > bounds.max.y && velocity.y > 0.0) { 0.0 } else { velocity.y }
+                    );
+                    self.objects[index].set_com(&clamped);
+                    self.objects[index].set_velocity(&velocity);
+                },
+                OutOfBounds::Event => {
+                    let body = self.remove_object(index);
+                    events.push(OutOfBoundsEvent {index, body});
+                }
+            }
+        }
+
+        events.reverse();
+        self.out_of_bounds_events.extend(events);
+    }
+
+    pub fn add_soft_body(&mut self, body: SoftBody) {
+        self.soft_bodies.push(body);
+    }
+
+    pub fn add_verlet_body(&mut self, body: VerletBody) {
+        self.verlet_bodies.push(body);
+    }
+
+    pub fn add_particle_system(&mut self, system: ParticleSystem) {
+        self.particle_systems.push(system);
+    }
+
+    pub fn add_fluid(&mut self, fluid: Fluid) {
+        self.fluids.push(fluid);
+    }
+
+    pub fn add_water_volume(&mut self, volume: WaterVolume) {
+        self.water_volumes.push(volume);
+    }
+
+    pub fn add_force_field(&mut self, field: ForceField) {
+        self.force_fields.push(field);
+    }
+
+    //Checks every body, point and particle for NaN or infinite state and reports the first one found
+    //Not called by update... run it after steps while debugging
+    pub fn validate(&self) -> Result<(), InvalidBodyState> {
+        let invalid = |collection, index, element, property| Err(InvalidBodyState {collection, index, element, property});
+
+        for (index, obj) in self.objects.iter().enumerate() {
+            let property = if !obj.get_com().is_finite() {
+                "center of mass"
+            } else if !obj.get_velocity().is_finite() {
+                "velocity"
+            } else if !obj.get_mass().is_finite() {
+                "mass"
+            } else {
+                continue;
+            };
+
+            return invalid("objects", index, None, property);
+        }
+        for (index, body) in self.soft_bodies.iter().enumerate() {
+            for (point, mass) in body.points.iter().enumerate() {
+                let property = if !mass.position.is_finite() {
+                    "position"
+                } else if !mass.velocity.is_finite() {
+                    "velocity"
+                } else if !mass.mass.is_finite() {
+                    "mass"
+                } else {
+                    continue;
+                };
+
+                return invalid("soft_bodies", index, Some(point), property);
+            }
+        }
+        for (index, body) in self.verlet_bodies.iter().enumerate() {
+            for (i, particle) in body.particles.iter().enumerate() {
+                let property = if !particle.position.is_finite() {
+                    "position"
+                } else if !particle.previous.is_finite() {
+                    "previous position"
+                } else if !particle.mass.is_finite() {
+                    "mass"
+                } else {
+                    continue;
+                };
+
+                return invalid("verlet_bodies", index, Some(i), property);
+            }
+        }
+        for (index, system) in self.particle_systems.iter().enumerate() {
+            for (i, (position, velocity)) in system.positions.iter().zip(system.velocities.iter()).enumerate() {
+                let property = if !position.is_finite() {
+                    "position"
+                } else if !velocity.is_finite() {
+                    "velocity"
+                } else {
+                    continue;
+                };
+
+                return invalid("particle_systems", index, Some(i), property);
+            }
+        }
+        for (index, fluid) in self.fluids.iter().enumerate() {
+            for (i, (position, velocity)) in fluid.positions.iter().zip(fluid.velocities.iter()).enumerate() {
+                let property = if !position.is_finite() {
+                    "position"
+                } else if !velocity.is_finite() {
+                    "velocity"
+                } else {
+                    continue;
+                };
+
+                return invalid("fluids", index, Some(i), property);
+            }
+        }
+
+        Ok(())
+    }
+
+    //FNV-1a hash of the exact bits of the world state... equal checksums mean identical simulations
+    //Random generators are included since they decide future particle emission and Langevin kicks
+    pub fn checksum(&self) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        {
+            let mut write = |value: Real| {
+                let bits = value.to_bits() as u64;
+                for i in 0..8 {
+                    hash ^= (bits >> (i * 8)) & 0xff;
+                    hash = hash.wrapping_mul(0x100000001b3);
+                }
+            };
+
+            write(self.gravity);
+            write(self.timestep);
+            for obj in self.objects.iter() {
+                let com = obj.get_com();
+                let velocity = obj.get_velocity();
+                write(com.x);
+                write(com.y);
+                write(velocity.x);
+                write(velocity.y);
+                write(obj.get_mass());
+            }
+            for body in self.soft_bodies.iter() {
+                for point in body.points.iter() {
+                    write(point.position.x);
+                    write(point.position.y);
+                    write(point.velocity.x);
+                    write(point.velocity.y);
+                    write(point.mass);
+                }
+            }
+            for body in self.verlet_bodies.iter() {
+                for particle in body.particles.iter() {
+                    write(particle.position.x);
+                    write(particle.position.y);
+                    write(particle.previous.x);
+                    write(particle.previous.y);
+                    write(particle.mass);
+                }
+            }
+            for system in self.particle_systems.iter() {
+                //Halves of every word fit exactly in an f32 Real
+                for &word in system.rng_state().iter() {
+                    write((word >> 16) as Real);
+                    write((word & 0xffff) as Real);
+                }
+                for (position, velocity) in system.positions.iter().zip(system.velocities.iter()) {
+                    write(position.x);
+                    write(position.y);
+                    write(velocity.x);
+                    write(velocity.y);
+                }
+            }
+            for fluid in self.fluids.iter() {
+                for (position, velocity) in fluid.positions.iter().zip(fluid.velocities.iter()) {
+                    write(position.x);
+                    write(position.y);
+                    write(velocity.x);
+                    write(velocity.y);
+                }
+            }
+            if let Some(ref molecular_dynamics) = self.molecular_dynamics {
+                for &word in molecular_dynamics.rng_state().iter() {
+                    write((word >> 16) as Real);
+                    write((word & 0xffff) as Real);
+                }
+            }
+        }
+
+        hash
+    }
+
+    //Indices of objects whose bounds overlap bounds
+    pub fn query(&self, bounds: &Aabb) -> Vec<usize> {
+        let mut results = Vec::new();
+        for (i, obj) in self.objects.iter().enumerate() {
+            if obj.bounds().overlaps(bounds) {
+                results.push(i);
+            }
+        }
+
+        results
+    }
+
+    //Bodies are always processed in the order of objects so repeated runs give the same results
     pub fn update(&mut self) {
+        //Apply force fields... explosions only act once
+        for field in self.force_fields.iter() {
+            field.apply(self.timestep, &mut self.objects);
+        }
+        self.force_fields.retain(|field| !(field.enabled && field.is_one_shot()));
+
+        if let Some(ref gravity) = self.mutual_gravity {
+            gravity.apply(self.timestep, &mut self.objects);
+        }
+        if let Some(ref electromagnetism) = self.electromagnetism {
+            electromagnetism.apply(self.timestep, &mut self.objects);
+        }
+        if let Some(ref mut molecular) = self.molecular_dynamics {
+            molecular.apply(self.timestep, &mut self.objects);
+        }
+
+        //Bodies only move at the end of the step so bounds can be computed once
+        let bounds: Vec<Aabb> = self.objects.iter().map(|obj| obj.bounds()).collect();
+
+        //Momentum handed to the walls... used for wall pressure
+        let mut wall_impulse = 0.0;
+
         //Check for collisions and change trajectories
         for i in 0..self.objects.len() {
             for j in (i+1)..self.objects.len() {
+                //In a periodic world j is tested at its image closest to i
+                let shift = match self.periodic {
+                    Some(periodic) => {
+                        let offset = self.objects[j].get_com().sub(&self.objects[i].get_com());
+                        periodic.minimum_image(&offset).sub(&offset)
+                    },
+                    None => Vec2D::new(0.0, 0.0)
+                };
+
+                //Skip the exact test for bodies that are far apart
+                if !bounds[i].overlaps(&bounds[j].translate(&shift)) {
+                    continue;
+                }
+
+                let original_com = self.objects[j].get_com();
+                let is_image = shift.x != 0.0 || shift.y != 0.0;
+                if is_image {
+                    self.objects[j].set_com(&original_com.add(&shift));
+                }
+
                 let has_collided = self.objects[i].has_collided(&*self.objects[j]);
                 let collision_direction = match self.objects[i].collision_direction(&*self.objects[j]) {
                     Some(v) => v,
@@ -121,7 +753,8 @@ impl World {
                 //Check if colliding objects are fixed/static and perform appropriate collision
                 if !self.objects[i].get_static() && !self.objects[j].get_static() {
                     //Perform elastic collision if non of the objects are static and have collided
-                    if has_collided {
+                    //Massless pairs have no defined outcome so they are skipped
+                    if has_collided && self.objects[i].get_mass() + self.objects[j].get_mass() > 0.0 {
                         let current_m = self.objects[i].get_mass();
                         let other_m = self.objects[j].get_mass();
 
@@ -158,6 +791,9 @@ impl World {
                                                         .mult(-1.0)
                                                         .add(&current_velocity.reject_on(&collision_direction));
                             self.objects[i].set_velocity(&new_velocity);
+                            if thermo::is_wall(&*self.objects[j]) {
+                                wall_impulse += thermo::wall_impulse(&*self.objects[j], &self.objects[i].get_com(), self.objects[i].get_mass(), &current_velocity, &new_velocity);
+                            }
                         }
 
                         if self.objects[j].get_static() {
@@ -168,17 +804,28 @@ impl World {
                                 .mult(-1.0)
                                 .add(&current_velocity.reject_on(&collision_direction));
                             self.objects[j].set_velocity(&new_velocity);
+                            if thermo::is_wall(&*self.objects[i]) {
+                                wall_impulse += thermo::wall_impulse(&*self.objects[i], &self.objects[j].get_com(), self.objects[j].get_mass(), &current_velocity, &new_velocity);
+                            }
                         }
                     }
                 }
+
+                if is_image {
+                    self.objects[j].set_com(&original_com);
+                }
             }
         }
 
+        if let Some(ref mut thermo) = self.thermo {
+            thermo.record_wall_impulse(wall_impulse);
+        }
+
         //Apply Frictional Force
         for obj in self.objects.iter_mut() {
             //Find magnitude of frictional force and make friction vector
             let friction_k = obj.get_mass() * self.gravity.abs() * obj.get_friction();
-            let friction_force = obj.get_velocity().unit().mult(friction_k * self.timestep);
+            let friction_force = obj.get_velocity().normalize_or_zero().mult(friction_k * self.timestep);
 
             //Makes sure that friction brings object to rest and not negative velocity
             if friction_force.mag() < obj.get_velocity().mag() {
@@ -189,6 +836,11 @@ impl World {
             }
         }
 
+        //Apply buoyancy and drag from water volumes
+        for volume in self.water_volumes.iter() {
+            volume.apply(self.timestep, &mut self.objects);
+        }
+
         //Update locations
         for obj in self.objects.iter_mut() {
             let com = obj.get_com();
@@ -196,5 +848,101 @@ impl World {
 
             obj.set_com(&com.add(&velocity.mult(self.timestep)));
         }
+        if let Some(ref molecular) = self.molecular_dynamics {
+            molecular.wrap_positions(&mut self.objects);
+        }
+        if let Some(periodic) = self.periodic {
+            for obj in self.objects.iter_mut() {
+                if obj.get_static() {
+                    continue;
+                }
+
+                let com = obj.get_com();
+                let wrapped = periodic.wrap(&com);
+                if wrapped != com {
+                    obj.set_com(&wrapped);
+                }
+            }
+        }
+
+        self.enforce_bounds();
+
+        //Soft bodies, ropes and cloth push against the rigid bodies after they have moved
+        for body in self.soft_bodies.iter_mut() {
+            body.step(self.timestep, &mut self.objects);
+        }
+        for body in self.verlet_bodies.iter_mut() {
+            body.step(self.timestep, &mut self.objects);
+        }
+        for system in self.particle_systems.iter_mut() {
+            system.step(self.timestep, &mut self.objects);
+        }
+        for fluid in self.fluids.iter_mut() {
+            fluid.step(self.timestep, &mut self.objects);
+        }
+
+        if let Some(ref mut thermo) = self.thermo {
+            thermo.sample(self.timestep, &self.objects);
+        }
+
+        //Taken out while sampling since the monitor reads the whole world
+        if let Some(mut monitor) = self.diagnostics.take() {
+            monitor.sample(self);
+            self.diagnostics = Some(monitor);
+        }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use physics::shapes::Circle;
+    use physics::particles::Emitter;
+    use physics::molecular::Thermostat;
+
+    //Bodies, an emitter and a Langevin thermostat so both random generators are used
+    fn world(seed: u32) -> World {
+        let mut world = World::new(0.0, 0.01);
+        for i in 0..6 {
+            let mut circle = Circle::new(1.0, Vec2D::new(i as Real * 1.1, (i % 2) as Real * 1.1), 0.2);
+            circle.velocity = Vec2D::new(0.5 - i as Real * 0.2, 0.3);
+            world.add_object(circle);
+        }
+
+        let mut molecular_dynamics = MolecularDynamics::new(1.0, 1.0);
+        molecular_dynamics.thermostat = Thermostat::Langevin {target: 1.0, friction: 0.5};
+        molecular_dynamics.seed(seed);
+        world.molecular_dynamics = Some(molecular_dynamics);
+
+        let mut particles = ParticleSystem::new(200);
+        particles.add_emitter(Emitter::new(Vec2D::new(0.0, 5.0), 100.0, 1.0, 0.5, 2.0, 1.0));
+        particles.seed(seed);
+        world.add_particle_system(particles);
+
+        world
+    }
+
+    #[test]
+    fn same_seed_gives_same_checksum() {
+        let mut a = world(7);
+        let mut b = world(7);
+        assert_eq!(a.checksum(), b.checksum());
+
+        for _ in 0..200 {
+            a.update();
+            b.update();
+            assert_eq!(a.checksum(), b.checksum());
+        }
+    }
+
+    #[test]
+    fn checksum_includes_random_state() {
+        //Worlds only differ in the seeds of their generators
+        let a = world(7);
+        let b = world(8);
+        assert!(a.checksum() != b.checksum());
+
+        let mut c = world(7);
+        c.molecular_dynamics.as_mut().unwrap().seed(8);
+        assert!(a.checksum() != c.checksum());
+    }
+}
@@ -0,0 +1,368 @@
+use physics::Vec2D;
+use physics::Real;
+use physics::bounds::Aabb;
+use physics::shapes::Circle;
+
+use renderer::RenderableObject;
+
+use rand::{Rng, SeedableRng, XorShiftRng};
+
+use std::collections::HashMap;
+
+use std::f64::consts::PI;
+
+//Keeps the temperature of the system near target
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Thermostat {
+    None,
+    //Rescales velocities towards target with relaxation time tau
+    Berendsen {target: Real, tau: Real},
+    //Adds friction and matching random kicks (friction is per second)
+    Langevin {target: Real, friction: Real}
+}
+
+//Measurements of the last step
+#[derive(Debug, Clone)]
+pub struct MolecularReport {
+    pub kinetic_energy: Real,
+    pub potential_energy: Real,
+    pub temperature: Real,
+    pub pressure: Real,
+    //Radial distribution function g(r)... bin i covers distances from i * rdf_bin_width to (i + 1) * rdf_bin_width
+    pub rdf: Vec<Real>,
+    pub rdf_bin_width: Real
+}
+
+//Lennard-Jones interactions between every non-static Circle in the world
+//V(r) = 4 epsilon ((sigma / r)^12 - (sigma / r)^6) cut off at cutoff... other shapes are ignored
+//Circle radii should be below sigma / 2 so the contact solver doesn't fight the potential
+#[derive(Clone)]
+pub struct MolecularDynamics {
+    //Depth of the potential well
+    pub epsilon: Real,
+    //Distance at which the potential is zero
+    pub sigma: Real,
+    pub cutoff: Real,
+    pub thermostat: Thermostat,
+    //Periodic simulation box... particles leaving one side come back on the other
+    pub periodic: Option<Aabb>,
+    //Boltzmann constant (1.0 in reduced units)
+    pub boltzmann: Real,
+    pub rdf_bins: usize,
+    pub report: MolecularReport,
+    rng: XorShiftRng
+}
+
+impl MolecularDynamics {
+    pub fn new(epsilon: Real, sigma: Real) -> MolecularDynamics {
+        MolecularDynamics {
+            epsilon,
+            sigma,
+            cutoff: 2.5 * sigma,
+            thermostat: Thermostat::None,
+            periodic: None,
+            boltzmann: 1.0,
+            rdf_bins: 50,
+            report: MolecularReport {
+                kinetic_energy: 0.0,
+                potential_energy: 0.0,
+                temperature: 0.0,
+                pressure: 0.0,
+                rdf: Vec::new(),
+                rdf_bin_width: 0.0
+            },
+            rng: XorShiftRng::from_seed([0x2f6b8a1d, 0x5e3c9b47, 0x91d4e2a3, 0x7ac1f05b])
+        }
+    }
+
+    //Random kicks of the Langevin thermostat are repeatable for the same seed
+    pub fn seed(&mut self, seed: u32) {
+        self.rng = XorShiftRng::from_seed([seed | 1, 0x5e3c9b47, 0x91d4e2a3, 0x7ac1f05b]);
+    }
+
+    //Next outputs of the generator without advancing it... equal states give equal Langevin kicks
+    pub fn rng_state(&self) -> [u32; 4] {
+        let mut rng = self.rng.clone();
+        [rng.next_u32(), rng.next_u32(), rng.next_u32(), rng.next_u32()]
+    }
+
+    //Force magnitude divided by distance and the potential energy at squared distance r2
+    fn pair(&self, r2: Real) -> (Real, Real) {
+        let s2 = self.sigma * self.sigma / r2;
+        let s6 = s2 * s2 * s2;
+        let s12 = s6 * s6;
+        (24.0 * self.epsilon * (2.0 * s12 - s6) / r2, 4.0 * self.epsilon * (s12 - s6))
+    }
+
+    //Shortest offset from b to a taking periodic images into account
+    pub fn minimum_image(&self, a: &Vec2D, b: &Vec2D) -> Vec2D {
+        match self.periodic {
+            Some(bounds) => bounds.minimum_image(&a.sub(b)),
+            None => a.sub(b)
+        }
+    }
+
+    //Every pair closer than cutoff as (i, j, offset from j to i) found with a cell list
+    //Only occupied cells are stored so far apart particles don't allocate the space between them
+    fn pairs(&self, positions: &[Vec2D]) -> Vec<(usize, usize, Vec2D)> {
+        let mut pairs = Vec::new();
+        //Nothing is within a cutoff that isn't positive
+        if positions.is_empty() || !(self.cutoff > 0.0) {
+            return pairs;
+        }
+
+        //Periodic boxes are split into whole cells so neighbors wrap around the seams
+        let (origin, columns, rows, cell_width, cell_height) = match self.periodic {
+            Some(bounds) => {
+                let columns = (((bounds.max.x - bounds.min.x) / self.cutoff).floor() as i64).max(1);
+                let rows = (((bounds.max.y - bounds.min.y) / self.cutoff).floor() as i64).max(1);
+                (bounds.min, columns, rows, (bounds.max.x - bounds.min.x) / columns as Real, (bounds.max.y - bounds.min.y) / rows as Real)
+            },
+            None => (Vec2D::new(0.0, 0.0), 0, 0, self.cutoff, self.cutoff)
+        };
+
+        let cell_of = |point: &Vec2D| {
+            let col = if cell_width > 0.0 { ((point.x - origin.x) / cell_width).floor() as i64 } else { 0 };
+            let row = if cell_height > 0.0 { ((point.y - origin.y) / cell_height).floor() as i64 } else { 0 };
+            if columns > 0 {
+                (col.max(0).min(columns - 1), row.max(0).min(rows - 1))
+            } else {
+                (col, row)
+            }
+        };
+
+        let mut cells: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+        for (i, point) in positions.iter().enumerate() {
+            cells.entry(cell_of(point)).or_insert_with(Vec::new).push(i);
+        }
+
+        let cutoff_squared = self.cutoff * self.cutoff;
+        let mut neighbors: Vec<(i64, i64)> = Vec::with_capacity(9);
+        for (i, point) in positions.iter().enumerate() {
+            let (col, row) = cell_of(point);
+
+            //Small boxes wrap onto the same cell more than once so duplicates are removed
+            neighbors.clear();
+            for dx in -1..2 {
+                for dy in -1..2 {
+                    let (mut c, mut r) = (col + dx, row + dy);
+                    if columns > 0 {
+                        c = (c + columns) % columns;
+                        r = (r + rows) % rows;
+                    }
+                    neighbors.push((c, r));
+                }
+            }
+            neighbors.sort();
+            neighbors.dedup();
+
+            for cell in neighbors.iter() {
+                let indices = match cells.get(cell) {
+                    Some(indices) => indices,
+                    None => continue
+                };
+                for &j in indices.iter() {
+                    if j <= i {
+                        continue;
+                    }
+
+                    let offset = self.minimum_image(point, &positions[j]);
+                    if offset.dot(&offset) < cutoff_squared {
+                        pairs.push((i, j, offset));
+                    }
+                }
+            }
+        }
+
+        pairs
+    }
+
+    //Standard normal sample (Box-Muller)
+    fn gaussian(&mut self) -> Real {
+        let u1 = self.rng.next_f64().max(1e-300);
+        let u2 = self.rng.next_f64();
+        ((-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()) as Real
+    }
+
+    //Applies Lennard-Jones forces and the thermostat over timestep and fills in report
+    pub fn apply(&mut self, timestep: Real, objects: &mut [Box<RenderableObject>]) {
+        let particles: Vec<usize> = (0..objects.len())
+            .filter(|&i| objects[i].as_any().is::<Circle>() && !objects[i].get_static() && objects[i].get_mass() > 0.0)
+            .collect();
+        let positions: Vec<Vec2D> = particles.iter().map(|&i| objects[i].get_com()).collect();
+        let masses: Vec<Real> = particles.iter().map(|&i| objects[i].get_mass()).collect();
+
+        let mut forces = vec![Vec2D::new(0.0, 0.0); particles.len()];
+        let mut potential_energy = 0.0;
+        let mut virial = 0.0;
+        let rdf_bin_width = self.cutoff / self.rdf_bins.max(1) as Real;
+        let mut histogram = vec![0.0; self.rdf_bins];
+        for (i, j, offset) in self.pairs(&positions) {
+            let r2 = offset.dot(&offset).max(1e-12);
+            let (force_over_r, potential) = self.pair(r2);
+            let force = offset.mult(force_over_r);
+            forces[i] = forces[i].add(&force);
+            forces[j] = forces[j].sub(&force);
+            potential_energy += potential;
+            virial += offset.dot(&force);
+
+            let bin = (r2.sqrt() / rdf_bin_width) as usize;
+            if bin < histogram.len() {
+                histogram[bin] += 1.0;
+            }
+        }
+
+        let mut velocities: Vec<Vec2D> = particles.iter().enumerate()
+            .map(|(k, &i)| objects[i].get_velocity().add(&forces[k].mult(timestep / masses[k])))
+            .collect();
+
+        let kinetic_energy = |velocities: &[Vec2D]| -> Real {
+            velocities.iter().zip(masses.iter()).map(|(v, &m)| 0.5 * m * v.dot(v)).sum()
+        };
+        //Two degrees of freedom per particle in 2D... KE = N k T
+        let count = particles.len() as Real;
+        let boltzmann = self.boltzmann;
+        let temperature_of = |kinetic: Real| if count > 0.0 { kinetic / (count * boltzmann) } else { 0.0 };
+
+        match self.thermostat {
+            Thermostat::None => (),
+            Thermostat::Berendsen {target, tau} => {
+                let temperature = temperature_of(kinetic_energy(&velocities));
+                if temperature > 0.0 && tau > 0.0 {
+                    let scale = (1.0 + timestep / tau * (target / temperature - 1.0)).max(0.0).sqrt();
+                    for velocity in velocities.iter_mut() {
+                        *velocity = velocity.mult(scale);
+                    }
+                }
+            },
+            Thermostat::Langevin {target, friction} => {
+                let damping = (1.0 - friction * timestep).max(0.0);
+                for k in 0..velocities.len() {
+                    let kick = (2.0 * friction * boltzmann * target * timestep / masses[k]).sqrt();
+                    let noise = Vec2D::new(self.gaussian(), self.gaussian());
+                    velocities[k] = velocities[k].mult(damping).add(&noise.mult(kick));
+                }
+            }
+        }
+
+        for (k, &i) in particles.iter().enumerate() {
+            objects[i].set_velocity(&velocities[k]);
+        }
+
+        //Open systems use the box around the particles as their volume
+        let bounds = match self.periodic {
+            Some(bounds) => bounds,
+            None => Aabb::from_points(&positions)
+        };
+        let area = (bounds.max.x - bounds.min.x) * (bounds.max.y - bounds.min.y);
+
+        let kinetic = kinetic_energy(&velocities);
+        let temperature = temperature_of(kinetic);
+        let density = if area > 0.0 { count / area } else { 0.0 };
+
+        //Virial pressure in 2D: P = (N k T + sum(r . F) / 2) / A
+        let pressure = if area > 0.0 { (count * self.boltzmann * temperature + 0.5 * virial) / area } else { 0.0 };
+
+        //Pair counts normalised by the count expected for an ideal gas at the same density
+        let rdf = histogram.iter().enumerate()
+            .map(|(bin, &pairs)| {
+                let inner = bin as Real * rdf_bin_width;
+                let outer = inner + rdf_bin_width;
+                let expected = 0.5 * count * density * PI as Real * (outer * outer - inner * inner);
+                if expected > 0.0 { pairs / expected } else { 0.0 }
+            })
+            .collect();
+
+        self.report = MolecularReport {
+            kinetic_energy: kinetic,
+            potential_energy,
+            temperature,
+            pressure,
+            rdf,
+            rdf_bin_width
+        };
+    }
+
+    //Wraps particles that left the periodic box... called after bodies have moved
+    pub fn wrap_positions(&self, objects: &mut [Box<RenderableObject>]) {
+        let bounds = match self.periodic {
+            Some(bounds) => bounds,
+            None => return
+        };
+
+        for obj in objects.iter_mut() {
+            if !obj.as_any().is::<Circle>() || obj.get_static() {
+                continue;
+            }
+
+            let com = obj.get_com();
+            let wrapped = bounds.wrap(&com);
+            if wrapped != com {
+                obj.set_com(&wrapped);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Scattered positions covering min to min + size
+    fn positions(count: usize, min: Vec2D, size: Real) -> Vec<Vec2D> {
+        (0..count)
+            .map(|i| {
+                let i = i as Real;
+                Vec2D::new(min.x + (i * 0.618034).fract() * size, min.y + (i * 0.754878).fract() * size)
+            })
+            .collect()
+    }
+
+    //Pairs found by the cell list as sorted (i, j) with i < j
+    fn cell_list_pairs(molecular: &MolecularDynamics, positions: &[Vec2D]) -> Vec<(usize, usize)> {
+        let mut pairs: Vec<(usize, usize)> = molecular.pairs(positions).iter().map(|&(i, j, _)| (i, j)).collect();
+        pairs.sort();
+        pairs
+    }
+
+    //Checks every pair against every other... O(n^2)
+    fn brute_force_pairs(molecular: &MolecularDynamics, positions: &[Vec2D]) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        for i in 0..positions.len() {
+            for j in (i + 1)..positions.len() {
+                let offset = molecular.minimum_image(&positions[i], &positions[j]);
+                if offset.dot(&offset) < molecular.cutoff * molecular.cutoff {
+                    pairs.push((i, j));
+                }
+            }
+        }
+        pairs
+    }
+
+    #[test]
+    fn cell_list_matches_brute_force() {
+        let molecular = MolecularDynamics::new(1.0, 1.0);
+        let positions = positions(300, Vec2D::new(-12.0, -7.0), 20.0);
+
+        let pairs = cell_list_pairs(&molecular, &positions);
+        assert!(!pairs.is_empty());
+        assert_eq!(pairs, brute_force_pairs(&molecular, &positions));
+    }
+
+    #[test]
+    fn periodic_cell_list_matches_brute_force() {
+        let mut molecular = MolecularDynamics::new(1.0, 1.0);
+        //Boxes that aren't a whole number of cutoffs wide and boxes only two cells wide wrap onto the same cells
+        for &(width, height) in [(20.0, 20.0), (17.3, 11.0), (6.0, 5.5)].iter() {
+            let min = Vec2D::new(-4.0, 3.0);
+            molecular.periodic = Some(Aabb::new(min, Vec2D::new(min.x + width, min.y + height)));
+            let positions: Vec<Vec2D> = positions(200, min, 1.0).iter()
+                .map(|point| Vec2D::new(min.x + (point.x - min.x) * width, min.y + (point.y - min.y) * height))
+                .collect();
+
+            let pairs = cell_list_pairs(&molecular, &positions);
+            assert!(!pairs.is_empty());
+            assert_eq!(pairs, brute_force_pairs(&molecular, &positions));
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use physics::Vec2D;
+use physics::Real;
+use physics::contact;
+use physics::bounds::Aabb;
+
+use renderer::RenderableObject;
+
+use rand::{Rng, SeedableRng, XorShiftRng};
+
+//Spawns particles at a steady rate
+#[derive(Clone)]
+pub struct Emitter {
+    pub position: Vec2D,
+    //Particles spawned per second
+    pub rate: Real,
+    //Angle of the emitted particles in radians and the maximum deviation to either side
+    pub direction: Real,
+    pub spread: Real,
+    //Speeds and lifetimes are picked uniformly between min and max
+    pub speed_min: Real,
+    pub speed_max: Real,
+    pub lifetime_min: Real,
+    pub lifetime_max: Real,
+    pub active: bool,
+    //Fraction of a particle left over from the previous step
+    accumulator: Real
+}
+
+//Large numbers of short lived points stored as parallel arrays
+//Particle i is made of positions[i], velocities[i], ages[i] and lifetimes[i]
+#[derive(Clone)]
+pub struct ParticleSystem {
+    pub positions: Vec<Vec2D>,
+    pub velocities: Vec<Vec2D>,
+    pub ages: Vec<Real>,
+    pub lifetimes: Vec<Real>,
+    pub emitters: Vec<Emitter>,
+    pub gravity: Vec2D,
+    //Fraction of the velocity lost per second
+    pub drag: Real,
+    //Particles bounce off world bodies without pushing them
+    pub collide: bool,
+    pub restitution: Real,
+    pub radius: Real,
+    //New particles are not spawned past this count
+    pub max_particles: usize,
+    pub color: [f32; 4],
+    rng: XorShiftRng
+}
+
+impl Emitter {
+    pub fn new(position: Vec2D, rate: Real, direction: Real, spread: Real, speed: Real, lifetime: Real) -> Emitter {
+        Emitter {
+            position,
+            rate,
+            direction,
+            spread,
+            speed_min: speed,
+            speed_max: speed,
+            lifetime_min: lifetime,
+            lifetime_max: lifetime,
+            active: true,
+            accumulator: 0.0
+        }
+    }
+}
+
+impl ParticleSystem {
+    pub fn new(max_particles: usize) -> ParticleSystem {
+        ParticleSystem {
+            positions: Vec::new(),
+            velocities: Vec::new(),
+            ages: Vec::new(),
+            lifetimes: Vec::new(),
+            emitters: Vec::new(),
+            gravity: Vec2D::new(0.0, 0.0),
+            drag: 0.0,
+            collide: false,
+            restitution: 0.5,
+            radius: 0.05,
+            max_particles,
+            color: [0.0, 0.0, 0.0, 1.0],
+            rng: XorShiftRng::from_seed([0x193a6754, 0xa8a7d469, 0x97830e05, 0x113ba7bb])
+        }
+    }
+
+    //Emission is random but repeatable... systems with the same seed spawn the same particles
+    pub fn seed(&mut self, seed: u32) {
+        //The generator needs a non zero state
+        self.rng = XorShiftRng::from_seed([seed | 1, 0xa8a7d469, 0x97830e05, 0x113ba7bb]);
+    }
+
+    //Next outputs of the generator without advancing it... equal states give equal emission
+    pub fn rng_state(&self) -> [u32; 4] {
+        let mut rng = self.rng.clone();
+        [rng.next_u32(), rng.next_u32(), rng.next_u32(), rng.next_u32()]
+    }
+
+    pub fn add_emitter(&mut self, emitter: Emitter) {
+        self.emitters.push(emitter);
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    //Adds a single particle... returns false when the system is full
+    pub fn spawn(&mut self, position: Vec2D, velocity: Vec2D, lifetime: Real) -> bool {
+        if self.len() >= self.max_particles {
+            return false;
+        }
+
+        self.positions.push(position);
+        self.velocities.push(velocity);
+        self.ages.push(0.0);
+        self.lifetimes.push(lifetime);
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.positions.clear();
+        self.velocities.clear();
+        self.ages.clear();
+        self.lifetimes.clear();
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        let extent = Vec2D::new(self.radius, self.radius);
+        let bounds = Aabb::from_points(&self.positions);
+        Aabb::new(bounds.min.sub(&extent), bounds.max.add(&extent))
+    }
+
+    //Uniform sample between min and max that also works when they are equal
+    fn sample(rng: &mut XorShiftRng, min: Real, max: Real) -> Real {
+        min + (max - min) * rng.next_f64() as Real
+    }
+
+    fn emit(&mut self, timestep: Real) {
+        for i in 0..self.emitters.len() {
+            if !self.emitters[i].active {
+                continue;
+            }
+
+            self.emitters[i].accumulator += self.emitters[i].rate * timestep;
+            while self.emitters[i].accumulator >= 1.0 {
+                self.emitters[i].accumulator -= 1.0;
+
+                let (position, angle, speed, lifetime) = {
+                    let emitter = &self.emitters[i];
+                    let rng = &mut self.rng;
+                    let angle = emitter.direction + ParticleSystem::sample(rng, -emitter.spread, emitter.spread);
+                    let speed = ParticleSystem::sample(rng, emitter.speed_min, emitter.speed_max);
+                    let lifetime = ParticleSystem::sample(rng, emitter.lifetime_min, emitter.lifetime_max);
+                    (emitter.position, angle, speed, lifetime)
+                };
+
+                let velocity = Vec2D::new(angle.cos(), angle.sin()).mult(speed);
+                if !self.spawn(position, velocity, lifetime) {
+                    //Drop the backlog instead of releasing a burst once there is room again
+                    self.emitters[i].accumulator = 0.0;
+                    break;
+                }
+            }
+        }
+    }
+
+    //Emits, moves and ages the particles... objects are only needed when collide is set
+    pub fn step(&mut self, timestep: Real, objects: &mut [Box<RenderableObject>]) {
+        self.emit(timestep);
+
+        let damping = (1.0 - self.drag * timestep).max(0.0);
+        for i in 0..self.positions.len() {
+            self.velocities[i] = self.velocities[i].add(&self.gravity.mult(timestep)).mult(damping);
+            self.positions[i] = self.positions[i].add(&self.velocities[i].mult(timestep));
+            self.ages[i] += timestep;
+
+            if self.collide {
+                //Zero mass makes the collision one-way
+                contact::collide_point(&mut self.positions[i], &mut self.velocities[i], 0.0, self.radius, self.restitution, objects);
+            }
+        }
+
+        //Swap dead particles with the last one so removal doesn't shift the arrays
+        let mut i = 0;
+        while i < self.positions.len() {
+            if self.ages[i] >= self.lifetimes[i] {
+                self.positions.swap_remove(i);
+                self.velocities.swap_remove(i);
+                self.ages.swap_remove(i);
+                self.lifetimes.swap_remove(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,520 @@
+use physics::Vec2D;
+use physics::Real;
+use physics::World;
+use physics::bounds::Aabb;
+use physics::bounds::OutOfBounds;
+use physics::shapes::*;
+
+use renderer::RenderableObject;
+
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::ser::Error as SerError;
+use serde::de::Error as DeError;
+use serde_json;
+
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::error::Error;
+use std::path::Path;
+
+//Bumped whenever the layout of a scene changes... load_scene rejects newer files
+pub const SCENE_VERSION: u32 = 1;
+
+//Bodies are stored tagged with their shape (ex. {"Circle": {...}})
+#[derive(Serialize)]
+enum BodyRef<'a> {
+    Circle(&'a Circle),
+    Line(&'a Line),
+    Polygon(&'a Polygon),
+    Chain(&'a Chain),
+    Heightfield(&'a Heightfield),
+    Group(&'a Group)
+}
+
+#[derive(Deserialize)]
+enum Body {
+    Circle(Circle),
+    Line(Line),
+    Polygon(Polygon),
+    Chain(Chain),
+    Heightfield(Heightfield),
+    Group(Group)
+}
+
+//Only the shapes in physics::shapes can be saved
+impl Serialize for Box<RenderableObject> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let body = self.as_any();
+        let tagged = if body.is::<Circle>() {
+            BodyRef::Circle(body.downcast_ref::<Circle>().unwrap())
+        } else if body.is::<Line>() {
+            BodyRef::Line(body.downcast_ref::<Line>().unwrap())
+        } else if body.is::<Polygon>() {
+            BodyRef::Polygon(body.downcast_ref::<Polygon>().unwrap())
+        } else if body.is::<Chain>() {
+            BodyRef::Chain(body.downcast_ref::<Chain>().unwrap())
+        } else if body.is::<Heightfield>() {
+            BodyRef::Heightfield(body.downcast_ref::<Heightfield>().unwrap())
+        } else if body.is::<Group>() {
+            BodyRef::Group(body.downcast_ref::<Group>().unwrap())
+        } else {
+            return Err(S::Error::custom("body type can't be serialized"));
+        };
+
+        tagged.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Box<RenderableObject> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match Body::deserialize(deserializer)? {
+            Body::Circle(circle) => Box::new(circle),
+            Body::Line(line) => Box::new(line),
+            Body::Polygon(polygon) => Box::new(polygon),
+            Body::Chain(chain) => Box::new(chain),
+            Body::Heightfield(heightfield) => Box::new(heightfield),
+            Body::Group(group) => Box::new(group)
+        })
+    }
+}
+
+//Polygons and Heightfields are rebuilt through their constructors so hand edited scenes are validated
+
+#[derive(Deserialize)]
+struct PolygonData {
+    points: Vec<Vec2D>,
+    mass: Real,
+    velocity: Vec2D,
+    color: [f32; 4],
+    friction: Real,
+    is_static: bool,
+    category: u32,
+    charge: Real,
+    out_of_bounds: OutOfBounds
+}
+
+impl<'de> Deserialize<'de> for Polygon {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = PolygonData::deserialize(deserializer)?;
+        let mut polygon = Polygon::new(data.points, data.mass).map_err(D::Error::custom)?;
+        polygon.velocity = data.velocity;
+        polygon.color = data.color;
+        polygon.friction = data.friction;
+        polygon.is_static = data.is_static;
+        polygon.category = data.category;
+        polygon.charge = data.charge;
+        polygon.out_of_bounds = data.out_of_bounds;
+
+        Ok(polygon)
+    }
+}
+
+#[derive(Deserialize)]
+struct HeightfieldData {
+    origin: Vec2D,
+    spacing: Real,
+    heights: Vec<Real>,
+    //Missing from scenes saved before depth was added
+    #[serde(default = "Heightfield::default_depth")]
+    depth: Real,
+    mass: Real,
+    velocity: Vec2D,
+    color: [f32; 4],
+    friction: Real,
+    is_static: bool,
+    category: u32,
+    charge: Real,
+    out_of_bounds: OutOfBounds
+}
+
+impl<'de> Deserialize<'de> for Heightfield {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = HeightfieldData::deserialize(deserializer)?;
+        let mut heightfield = Heightfield::new(data.origin, data.spacing, data.heights).map_err(D::Error::custom)?;
+        heightfield.depth = data.depth;
+        heightfield.mass = data.mass;
+        heightfield.velocity = data.velocity;
+        heightfield.color = data.color;
+        heightfield.friction = data.friction;
+        heightfield.is_static = data.is_static;
+        heightfield.category = data.category;
+        heightfield.charge = data.charge;
+        heightfield.out_of_bounds = data.out_of_bounds;
+
+        Ok(heightfield)
+    }
+}
+
+//Chains and Groups keep cached bounds that aren't saved and are rebuilt on load
+
+#[derive(Serialize)]
+struct ChainRef<'a> {
+    points: &'a [Vec2D],
+    is_loop: bool,
+    ghost_start: Option<Vec2D>,
+    ghost_end: Option<Vec2D>,
+    mass: Real,
+    velocity: Vec2D,
+    color: [f32; 4],
+    friction: Real,
+    is_static: bool,
+    category: u32,
+    charge: Real,
+    out_of_bounds: OutOfBounds
+}
+
+#[derive(Deserialize)]
+struct ChainData {
+    points: Vec<Vec2D>,
+    is_loop: bool,
+    ghost_start: Option<Vec2D>,
+    ghost_end: Option<Vec2D>,
+    mass: Real,
+    velocity: Vec2D,
+    color: [f32; 4],
+    friction: Real,
+    is_static: bool,
+    category: u32,
+    charge: Real,
+    out_of_bounds: OutOfBounds
+}
+
+impl Serialize for Chain {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ChainRef {
+            points: &self.points,
+            is_loop: self.is_loop,
+            ghost_start: self.ghost_start,
+            ghost_end: self.ghost_end,
+            mass: self.mass,
+            velocity: self.velocity,
+            color: self.color,
+            friction: self.friction,
+            is_static: self.is_static,
+            category: self.category,
+            charge: self.charge,
+            out_of_bounds: self.out_of_bounds
+        }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Chain {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = ChainData::deserialize(deserializer)?;
+        let mut chain = Chain::new(data.points);
+        chain.is_loop = data.is_loop;
+        chain.ghost_start = data.ghost_start;
+        chain.ghost_end = data.ghost_end;
+        chain.mass = data.mass;
+        chain.velocity = data.velocity;
+        chain.color = data.color;
+        chain.friction = data.friction;
+        chain.is_static = data.is_static;
+        chain.category = data.category;
+        chain.charge = data.charge;
+        chain.out_of_bounds = data.out_of_bounds;
+        chain.rebuild_bounds();
+
+        Ok(chain)
+    }
+}
+
+#[derive(Serialize)]
+struct GroupRef<'a> {
+    objects: &'a [Box<RenderableObject>],
+    com: Vec2D,
+    mass: Real,
+    velocity: Vec2D,
+    friction: Real,
+    is_static: bool,
+    category: u32,
+    charge: Real,
+    out_of_bounds: OutOfBounds
+}
+
+#[derive(Deserialize)]
+struct GroupData {
+    objects: Vec<Box<RenderableObject>>,
+    com: Vec2D,
+    mass: Real,
+    velocity: Vec2D,
+    friction: Real,
+    is_static: bool,
+    category: u32,
+    charge: Real,
+    out_of_bounds: OutOfBounds
+}
+
+impl Serialize for Group {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        GroupRef {
+            objects: &self.objects,
+            com: self.com,
+            mass: self.mass,
+            velocity: self.velocity,
+            friction: self.friction,
+            is_static: self.is_static,
+            category: self.category,
+            charge: self.charge,
+            out_of_bounds: self.out_of_bounds
+        }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Group {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = GroupData::deserialize(deserializer)?;
+        let mut group = Group::new();
+        group.objects = data.objects;
+        group.com = data.com;
+        group.mass = data.mass;
+        group.velocity = data.velocity;
+        group.friction = data.friction;
+        group.is_static = data.is_static;
+        group.category = data.category;
+        group.charge = data.charge;
+        group.out_of_bounds = data.out_of_bounds;
+        group.update_bounds();
+
+        Ok(group)
+    }
+}
+
+//Only the world settings and rigid bodies are saved
+//Soft bodies, fluids, fields and the other subsystems are still set up in code after loading
+#[derive(Serialize)]
+struct WorldRef<'a> {
+    gravity: Real,
+    timestep: Real,
+    objects: &'a [Box<RenderableObject>],
+    periodic: Option<Aabb>,
+    bounds: Option<Aabb>
+}
+
+#[derive(Deserialize)]
+struct WorldData {
+    gravity: Real,
+    timestep: Real,
+    objects: Vec<Box<RenderableObject>>,
+    periodic: Option<Aabb>,
+    bounds: Option<Aabb>
+}
+
+impl Serialize for World {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        WorldRef {
+            gravity: self.gravity,
+            timestep: self.timestep,
+            objects: &self.objects,
+            periodic: self.periodic,
+            bounds: self.bounds
+        }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for World {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = WorldData::deserialize(deserializer)?;
+        let mut world = World::new(data.gravity, data.timestep);
+        world.objects = data.objects;
+        world.periodic = data.periodic;
+        world.bounds = data.bounds;
+
+        Ok(world)
+    }
+}
+
+#[derive(Serialize)]
+struct SceneRef<'a> {
+    version: u32,
+    world: &'a World
+}
+
+#[derive(Deserialize)]
+struct SceneVersion {
+    version: u32
+}
+
+#[derive(Deserialize)]
+struct Scene {
+    world: World
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    Format(serde_json::Error),
+    //Scene was written by a newer version of the format
+    UnsupportedVersion(u32)
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SceneError::Io(ref error) => write!(f, "couldn't access scene: {}", error),
+            SceneError::Format(ref error) => write!(f, "malformed scene: {}", error),
+            SceneError::UnsupportedVersion(version) => write!(f, "scene version {} is newer than the supported version {}", version, SCENE_VERSION)
+        }
+    }
+}
+
+impl Error for SceneError {}
+
+impl From<io::Error> for SceneError {
+    fn from(error: io::Error) -> SceneError {
+        SceneError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for SceneError {
+    fn from(error: serde_json::Error) -> SceneError {
+        SceneError::Format(error)
+    }
+}
+
+//Scene as pretty printed JSON... {"version": 1, "world": {...}}
+pub fn scene_to_string(world: &World) -> Result<String, SceneError> {
+    Ok(serde_json::to_string_pretty(&SceneRef {version: SCENE_VERSION, world})?)
+}
+
+pub fn scene_from_str(scene: &str) -> Result<World, SceneError> {
+    //The version is read on its own first so newer files fail with a clear error instead of a format error
+    let header: SceneVersion = serde_json::from_str(scene)?;
+    if header.version > SCENE_VERSION {
+        return Err(SceneError::UnsupportedVersion(header.version));
+    }
+
+    let scene: Scene = serde_json::from_str(scene)?;
+    Ok(scene.world)
+}
+
+pub fn save_scene<W: Write>(world: &World, writer: &mut W) -> Result<(), SceneError> {
+    writer.write_all(scene_to_string(world)?.as_bytes())?;
+    Ok(())
+}
+
+pub fn load_scene<R: Read>(reader: &mut R) -> Result<World, SceneError> {
+    let mut scene = String::new();
+    reader.read_to_string(&mut scene)?;
+    scene_from_str(&scene)
+}
+
+pub fn save_scene_file<P: AsRef<Path>>(world: &World, path: P) -> Result<(), SceneError> {
+    save_scene(world, &mut File::create(path)?)
+}
+
+pub fn load_scene_file<P: AsRef<Path>>(path: P) -> Result<World, SceneError> {
+    load_scene(&mut File::open(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use physics::Object;
+
+    //One of every shape with a group nested inside another group
+    fn world() -> World {
+        let mut world = World::new(9.8, 0.02);
+        world.bounds = Some(Aabb::new(Vec2D::new(-20.0, -20.0), Vec2D::new(20.0, 20.0)));
+
+        let mut circle = Circle::new(2.0, Vec2D::new(-5.0, 0.25), 1.0);
+        circle.velocity = Vec2D::new(4.0, -1.0);
+        circle.charge = 0.5;
+        circle.out_of_bounds = OutOfBounds::Clamp;
+        world.add_object(circle);
+
+        let mut line = Line::new(Vec2D::new(-8.0, 5.0), Vec2D::new(-2.0, 6.0));
+        line.category = 4;
+        world.add_object(line);
+
+        let mut polygon = Polygon::new(vec![
+            Vec2D::new(5.0, 0.0),
+            Vec2D::new(7.0, 0.0),
+            Vec2D::new(7.0, 2.0),
+            Vec2D::new(5.0, 2.0)
+        ], 3.0).unwrap();
+        polygon.color = [1.0, 0.5, 0.25, 1.0];
+        world.add_object(polygon);
+
+        let mut chain = Chain::new(vec![Vec2D::new(-15.0, -15.0), Vec2D::new(0.0, -14.0), Vec2D::new(15.0, -15.0)]);
+        chain.ghost_start = Some(Vec2D::new(-20.0, -15.0));
+        world.add_object(chain);
+
+        let mut heightfield = Heightfield::new(Vec2D::new(-10.0, -14.0), 0.5, vec![0.0, 1.0, 0.5, 0.25]).unwrap();
+        heightfield.depth = 5.0;
+        world.add_object(heightfield);
+
+        let mut inner = Group::new();
+        inner.add_object(Circle::new(1.0, Vec2D::new(0.0, 0.0), 0.5));
+        inner.add_object(Group::create_concave_polygon(vec![
+            Vec2D::new(2.0, 0.0),
+            Vec2D::new(4.0, 0.0),
+            Vec2D::new(4.0, 1.0),
+            Vec2D::new(3.0, 1.0),
+            Vec2D::new(3.0, 2.0),
+            Vec2D::new(2.0, 2.0)
+        ], 2.0).unwrap());
+        let mut group = Group::new();
+        group.add_object(inner);
+        group.add_object(Line::new(Vec2D::new(3.0, 0.0), Vec2D::new(3.0, 1.0)));
+        group.set_velocity(&Vec2D::new(3.0, 2.0));
+        world.add_object(group);
+
+        world
+    }
+
+    #[test]
+    fn every_shape_round_trips() {
+        let world = world();
+        let scene = scene_to_string(&world).unwrap();
+        let loaded = scene_from_str(&scene).unwrap();
+
+        assert_eq!(loaded.checksum(), world.checksum());
+        assert_eq!(loaded.bounds, world.bounds);
+        //Properties missing from the checksum must survive too
+        assert_eq!(loaded.objects[2].as_any().downcast_ref::<Polygon>().unwrap().color, [1.0, 0.5, 0.25, 1.0]);
+        assert_eq!(loaded.objects[3].as_any().downcast_ref::<Chain>().unwrap().ghost_start, Some(Vec2D::new(-20.0, -15.0)));
+        assert_eq!(loaded.objects[4].as_any().downcast_ref::<Heightfield>().unwrap().depth, 5.0);
+
+        let group = loaded.objects[5].as_any().downcast_ref::<Group>().unwrap();
+        let inner = group.objects[0].as_any().downcast_ref::<Group>().unwrap();
+        assert!(inner.objects[0].as_any().is::<Circle>());
+        assert!(inner.objects[1].as_any().is::<Group>());
+    }
+
+    #[test]
+    fn invalid_shapes_are_rejected() {
+        let mut collinear = Polygon::new(vec![
+            Vec2D::new(0.0, 0.0),
+            Vec2D::new(1.0, 0.0),
+            Vec2D::new(1.0, 1.0)
+        ], 1.0).unwrap();
+        collinear.points[2] = Vec2D::new(2.0, 0.0);
+
+        let mut concave = Polygon::new(vec![
+            Vec2D::new(0.0, 0.0),
+            Vec2D::new(2.0, 0.0),
+            Vec2D::new(2.0, 2.0),
+            Vec2D::new(0.0, 2.0)
+        ], 1.0).unwrap();
+        concave.points[2] = Vec2D::new(1.0, 0.5);
+
+        let mut spacing = Heightfield::new(Vec2D::new(0.0, 0.0), 1.0, vec![0.0, 1.0]).unwrap();
+        spacing.spacing = 0.0;
+        let mut heights = spacing.clone();
+        heights.spacing = 1.0;
+        heights.heights.pop();
+
+        let bodies: Vec<Box<RenderableObject>> = vec![Box::new(collinear), Box::new(concave), Box::new(spacing), Box::new(heights)];
+        for body in bodies {
+            let mut world = World::new(9.8, 0.02);
+            world.objects.push(body);
+            match scene_from_str(&scene_to_string(&world).unwrap()) {
+                Err(SceneError::Format(_)) => {},
+                _ => panic!("invalid body was loaded")
+            }
+        }
+    }
+}
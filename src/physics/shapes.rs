@@ -1,51 +1,159 @@
 use physics::Vec2D;
+use physics::Real;
 use physics::Object;
 use physics::Collidable;
+use physics::geometry;
+use physics::geometry::PolygonError;
+use physics::bounds::Aabb;
+use physics::bounds::AabbTree;
+use physics::bounds::OutOfBounds;
 
 use renderer::RenderableObject;
 
 use std::any::Any;
+use std::error::Error;
+use std::fmt;
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Circle {
-    pub mass: f64,
+    pub mass: Real,
     pub velocity: Vec2D,
     pub center: Vec2D,
-    pub radius: f64,
+    pub radius: Real,
     pub color: [f32; 4],
-    pub friction: f64,
+    pub friction: Real,
     pub is_static: bool,
+    //Bit mask of the categories the body belongs to... force fields only affect matching bodies
+    pub category: u32,
+    //Electric charge used by physics::electromagnetism
+    pub charge: Real,
+    //What World does with the body once it leaves World::bounds
+    pub out_of_bounds: OutOfBounds
 }
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Line {
     pub start_point: Vec2D,
     pub end_point: Vec2D,
-    pub mass: f64,
+    pub mass: Real,
     pub velocity: Vec2D,
     pub color: [f32; 4],
-    pub friction: f64,
-    pub is_static: bool
+    pub friction: Real,
+    pub is_static: bool,
+    pub category: u32,
+    pub charge: Real,
+    pub out_of_bounds: OutOfBounds
+}
+
+//Convex polygon with points stored in counter-clockwise order
+#[derive(Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+pub struct Polygon {
+    pub points: Vec<Vec2D>,
+    pub mass: Real,
+    pub velocity: Vec2D,
+    pub color: [f32; 4],
+    pub friction: Real,
+    pub is_static: bool,
+    pub category: u32,
+    pub charge: Real,
+    pub out_of_bounds: OutOfBounds
+}
+
+//Connected series of segments used for static terrain
+//Shared vertices are smoothed using the neighbouring (ghost) vertices so bodies do not snag on seams
+#[derive(Clone)]
+pub struct Chain {
+    pub points: Vec<Vec2D>,
+    pub is_loop: bool,
+    //Vertices before the first point and after the last point of an open chain (ex. from a connecting chain)
+    pub ghost_start: Option<Vec2D>,
+    pub ghost_end: Option<Vec2D>,
+    pub mass: Real,
+    pub velocity: Vec2D,
+    pub color: [f32; 4],
+    pub friction: Real,
+    pub is_static: bool,
+    pub category: u32,
+    pub charge: Real,
+    pub out_of_bounds: OutOfBounds,
+    segment_tree: AabbTree
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HeightfieldError {
+    //Spacing between heights is zero, negative or NaN
+    InvalidSpacing,
+    //Fewer than two heights can't form a column
+    TooFewHeights,
+}
+
+impl fmt::Display for HeightfieldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HeightfieldError::InvalidSpacing => write!(f, "heightfield spacing must be positive"),
+            HeightfieldError::TooFewHeights => write!(f, "heightfield needs at least two heights"),
+        }
+    }
+}
+
+impl Error for HeightfieldError {}
+
+//Terrain made of regularly spaced heights... everything below the surface is solid
+//Height i is located at x = origin.x + i * spacing and y = origin.y + heights[i]
+#[derive(Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+pub struct Heightfield {
+    pub origin: Vec2D,
+    pub spacing: Real,
+    pub heights: Vec<Real>,
+    //How far below the lowest height the bounds reach... bodies deeper than this are missed by the broad phase
+    pub depth: Real,
+    pub mass: Real,
+    pub velocity: Vec2D,
+    pub color: [f32; 4],
+    pub friction: Real,
+    pub is_static: bool,
+    pub category: u32,
+    pub charge: Real,
+    pub out_of_bounds: OutOfBounds
 }
 
+#[derive(Clone)]
 pub struct Group {
     pub objects: Vec<Box<RenderableObject>>,
     pub com: Vec2D,
-    pub mass: f64,
+    pub mass: Real,
     pub velocity: Vec2D,
-    pub friction: f64,
-    pub is_static: bool
+    pub friction: Real,
+    pub is_static: bool,
+    pub category: u32,
+    pub charge: Real,
+    pub out_of_bounds: OutOfBounds,
+    //Cached union of the member bounds
+    bounds: Aabb
 }
 
 impl Circle {
-    pub fn new(mass: f64, center: Vec2D, radius: f64) -> Circle{
+    pub fn new(mass: Real, center: Vec2D, radius: Real) -> Circle{
         Circle{ mass,
                 velocity: Vec2D::new(0.0, 0.0),
                 center,
                 radius,
                 color: [0.0, 0.0, 0.0, 1.0],
                 friction: 0.0,
-                is_static: false
+                is_static: false,
+                category: 1,
+                charge: 0.0,
+                out_of_bounds: OutOfBounds::Despawn
         }
     }
+
+    pub fn area(&self) -> Real {
+        ::std::f64::consts::PI as Real * self.radius * self.radius
+    }
 }
 
 impl Line {
@@ -58,7 +166,403 @@ impl Line {
             color: [0.0, 0.0, 0.0, 1.0],
             friction: 0.0,
             is_static: true,
+            category: 1,
+            charge: 0.0,
+            out_of_bounds: OutOfBounds::Despawn,
+        }
+    }
+}
+
+impl Polygon {
+    //Points must describe a convex polygon... use Group::create_concave_polygon for arbitrary outlines
+    //Points are stored counter-clockwise with a repeated closing point and collinear points removed
+    pub fn new(points: Vec<Vec2D>, mass: Real) -> Result<Polygon, PolygonError> {
+        let points = geometry::validate_convex(&points)?;
+
+        Ok(Polygon {
+            points,
+            mass,
+            velocity: Vec2D::new(0.0, 0.0),
+            color: [0.0, 0.0, 0.0, 1.0],
+            friction: 0.0,
+            is_static: false,
+            category: 1,
+            charge: 0.0,
+            out_of_bounds: OutOfBounds::Despawn,
+        })
+    }
+
+    pub fn area(&self) -> Real {
+        geometry::signed_area(&self.points).abs()
+    }
+
+    //Returns the axis between the polygon and circle if they overlap
+    fn circle_contact(&self, circle: &Circle) -> Option<Vec2D> {
+        //Points are public so they may have been emptied after construction
+        if self.points.is_empty() {
+            return None;
+        }
+
+        let mut closest_point = self.points[0];
+        let mut closest_distance = Real::INFINITY;
+        for i in 0..self.points.len() {
+            let point = geometry::closest_point_on_segment(&circle.center, &self.points[i], &self.points[(i + 1) % self.points.len()]);
+            let distance = point.sub(&circle.center).mag();
+            if distance < closest_distance {
+                closest_distance = distance;
+                closest_point = point;
+            }
+        }
+
+        let inside = geometry::point_in_convex(&circle.center, &self.points);
+        if !inside && closest_distance >= circle.radius {
+            return None;
+        }
+
+        if closest_distance > 0.0 {
+            Some(closest_point.sub(&circle.center))
+        } else {
+            //Center lies exactly on the boundary so push along the direction from the polygon's center
+            Some(circle.center.sub(&self.get_com()))
+        }
+    }
+}
+
+impl Chain {
+    //Open polyline through points
+    pub fn new(points: Vec<Vec2D>) -> Chain {
+        let mut chain = Chain {
+            points,
+            is_loop: false,
+            ghost_start: None,
+            ghost_end: None,
+            mass: 1.0,
+            velocity: Vec2D::new(0.0, 0.0),
+            color: [0.0, 0.0, 0.0, 1.0],
+            friction: 0.0,
+            is_static: true,
+            category: 1,
+            charge: 0.0,
+            out_of_bounds: OutOfBounds::Despawn,
+            segment_tree: AabbTree::new(&[])
+        };
+        chain.rebuild_bounds();
+
+        chain
+    }
+
+    //Closed loop through points... the last point connects back to the first
+    pub fn new_loop(points: Vec<Vec2D>) -> Chain {
+        let mut chain = Chain::new(points);
+        chain.is_loop = true;
+        chain.rebuild_bounds();
+
+        chain
+    }
+
+    //Must be called after points or is_loop are modified directly
+    pub fn rebuild_bounds(&mut self) {
+        let segment_bounds: Vec<Aabb> = (0..self.segment_count())
+            .map(|i| {
+                let (start, end) = self.segment(i);
+                Aabb::from_points(&[start, end])
+            })
+            .collect();
+        self.segment_tree = AabbTree::new(&segment_bounds);
+    }
+
+    pub fn segment_count(&self) -> usize {
+        if self.points.len() < 2 {
+            0
+        } else if self.is_loop {
+            self.points.len()
+        } else {
+            self.points.len() - 1
+        }
+    }
+
+    pub fn segment(&self, i: usize) -> (Vec2D, Vec2D) {
+        (self.points[i], self.points[(i + 1) % self.points.len()])
+    }
+
+    //Indices of segments whose bounds might overlap bounds
+    pub fn query(&self, bounds: &Aabb) -> Vec<usize> {
+        let mut candidates = Vec::new();
+        self.segment_tree.query(bounds, &mut candidates);
+
+        candidates
+    }
+
+    //Vertex connected to the start of segment i
+    fn vertex_before(&self, i: usize) -> Option<Vec2D> {
+        if self.is_loop {
+            Some(self.points[(i + self.points.len() - 1) % self.points.len()])
+        } else if i == 0 {
+            self.ghost_start
+        } else {
+            Some(self.points[i - 1])
+        }
+    }
+
+    //Vertex connected to the end of segment i
+    fn vertex_after(&self, i: usize) -> Option<Vec2D> {
+        if self.is_loop {
+            Some(self.points[(i + 2) % self.points.len()])
+        } else if i + 2 >= self.points.len() {
+            self.ghost_end
+        } else {
+            Some(self.points[i + 2])
+        }
+    }
+
+    //Returns whether the corner at the start/end of segment i bends away from a body on the given side of the segment
+    //Only convex corners may push bodies along a direction other than the segment normal
+    fn is_convex_corner(&self, i: usize, at_start: bool, side: Real) -> bool {
+        let (start, end) = self.segment(i);
+        let direction = end.sub(&start);
+
+        let turn = if at_start {
+            match self.vertex_before(i) {
+                Some(ghost) => geometry::orientation(&ghost, &start, &end),
+                None => return true
+            }
+        } else {
+            match self.vertex_after(i) {
+                Some(ghost) => geometry::orientation(&start, &end, &ghost),
+                None => return true
+            }
+        };
+
+        //Normalise so the threshold does not depend on segment lengths
+        turn / direction.mag() * side < -geometry::EPSILON
+    }
+
+    //Side (+1.0/-1.0) of segment i that point lies on
+    fn side_of(&self, i: usize, point: &Vec2D) -> Real {
+        let (start, end) = self.segment(i);
+        if geometry::orientation(&start, &end, point) < 0.0 { -1.0 } else { 1.0 }
+    }
+
+    fn circle_contact(&self, circle: &Circle) -> Option<Vec2D> {
+        let radius = Vec2D::new(circle.radius, circle.radius);
+        let bounds = Aabb::new(circle.center.sub(&radius), circle.center.add(&radius));
+
+        //Find the single closest segment so seams only produce one contact
+        let mut closest: Option<(usize, Real, Real)> = None;
+        for i in self.query(&bounds) {
+            let (start, end) = self.segment(i);
+            let t = geometry::closest_parameter_on_segment(&circle.center, &start, &end);
+            let distance = start.add(&end.sub(&start).mult(t)).sub(&circle.center).mag();
+            let is_closer = match closest {
+                Some((_, _, closest_distance)) => distance < closest_distance,
+                None => true
+            };
+            if is_closer {
+                closest = Some((i, t, distance));
+            }
+        }
+
+        let (i, t, distance) = match closest {
+            Some(contact) => contact,
+            None => return None
+        };
+        if distance >= circle.radius {
+            return None;
+        }
+
+        let (start, end) = self.segment(i);
+        let normal = end.sub(&start).perp();
+        let side = self.side_of(i, &circle.center);
+        let at_vertex = t <= 0.0 || t >= 1.0;
+
+        if distance == 0.0 || (at_vertex && !self.is_convex_corner(i, t <= 0.0, side)) {
+            return Some(normal);
+        }
+
+        Some(start.add(&end.sub(&start).mult(t)).sub(&circle.center))
+    }
+
+    //Contact between the chain and a convex point set (polygon or line)
+    fn convex_contact(&self, points: &[Vec2D]) -> Option<Vec2D> {
+        let bounds = Aabb::from_points(points);
+        let center = geometry::centroid(points);
+
+        //Use the segment with the deepest penetration
+        let mut deepest: Option<(usize, Vec2D, Real)> = None;
+        for i in self.query(&bounds) {
+            let (start, end) = self.segment(i);
+            if let Some((axis, depth)) = geometry::sat_contact(points, &[start, end]) {
+                let is_deeper = match deepest {
+                    Some((_, _, deepest_depth)) => depth > deepest_depth,
+                    None => true
+                };
+                if is_deeper {
+                    deepest = Some((i, axis, depth));
+                }
+            }
+        }
+
+        let (i, axis, _) = match deepest {
+            Some(contact) => contact,
+            None => return None
+        };
+
+        let (start, end) = self.segment(i);
+        let normal = match end.sub(&start).perp().try_unit() {
+            Some(normal) => normal,
+            None => return Some(axis)
+        };
+        if axis.dot(&normal).abs() < 1.0 - geometry::EPSILON {
+            //Axis comes from a vertex of the segment... only keep it if that vertex is a convex corner
+            let at_start = geometry::closest_parameter_on_segment(&center, &start, &end) < 0.5;
+            if !self.is_convex_corner(i, at_start, self.side_of(i, &center)) {
+                return Some(normal);
+            }
+        }
+
+        Some(axis)
+    }
+}
+
+impl Heightfield {
+    //Needs a positive spacing and at least two heights to form a column
+    pub fn new(origin: Vec2D, spacing: Real, heights: Vec<Real>) -> Result<Heightfield, HeightfieldError> {
+        if !(spacing > 0.0) {
+            return Err(HeightfieldError::InvalidSpacing);
+        }
+        if heights.len() < 2 {
+            return Err(HeightfieldError::TooFewHeights);
+        }
+
+        Ok(Heightfield {
+            origin,
+            spacing,
+            heights,
+            depth: Heightfield::default_depth(),
+            mass: 1.0,
+            velocity: Vec2D::new(0.0, 0.0),
+            color: [0.0, 0.0, 0.0, 1.0],
+            friction: 0.0,
+            is_static: true,
+            category: 1,
+            charge: 0.0,
+            out_of_bounds: OutOfBounds::Despawn,
+        })
+    }
+
+    pub fn default_depth() -> Real {
+        100.0
+    }
+
+    pub fn column_count(&self) -> usize {
+        if self.heights.len() < 2 { 0 } else { self.heights.len() - 1 }
+    }
+
+    //Surface segment of column i
+    pub fn column(&self, i: usize) -> (Vec2D, Vec2D) {
+        (
+            Vec2D::new(self.origin.x + i as Real * self.spacing, self.origin.y + self.heights[i]),
+            Vec2D::new(self.origin.x + (i + 1) as Real * self.spacing, self.origin.y + self.heights[i + 1])
+        )
+    }
+
+    //Columns overlapping the horizontal range [min_x, max_x]... found directly from the spacing
+    pub fn column_range(&self, min_x: Real, max_x: Real) -> Option<(usize, usize)> {
+        if self.column_count() == 0 {
+            return None;
+        }
+
+        let first = ((min_x - self.origin.x) / self.spacing).floor();
+        let last = ((max_x - self.origin.x) / self.spacing).floor();
+        if last < 0.0 || first >= self.column_count() as Real {
+            return None;
+        }
+
+        let first = first.max(0.0) as usize;
+        let last = (last as usize).min(self.column_count() - 1);
+
+        Some((first, last))
+    }
+
+    //Interpolated surface height at x
+    pub fn height_at(&self, x: Real) -> Option<Real> {
+        let (i, _) = match self.column_range(x, x) {
+            Some(range) => range,
+            None => return None
+        };
+        let (start, end) = self.column(i);
+        let t = ((x - start.x) / self.spacing).max(0.0).min(1.0);
+
+        Some(start.y + (end.y - start.y) * t)
+    }
+
+    //Upward facing normal of column i
+    pub fn column_normal(&self, i: usize) -> Vec2D {
+        let (start, end) = self.column(i);
+        end.sub(&start).perp()
+    }
+
+    fn circle_contact(&self, circle: &Circle) -> Option<Vec2D> {
+        let (first, last) = match self.column_range(circle.center.x - circle.radius, circle.center.x + circle.radius) {
+            Some(range) => range,
+            None => return None
+        };
+
+        //Circles that tunnelled below the surface are pushed straight out
+        if let Some(height) = self.height_at(circle.center.x) {
+            if circle.center.y <= height {
+                let (i, _) = self.column_range(circle.center.x, circle.center.x).unwrap();
+                return Some(self.column_normal(i));
+            }
+        }
+
+        let mut closest_point = circle.center;
+        let mut closest_distance = Real::INFINITY;
+        for i in first..(last + 1) {
+            let (start, end) = self.column(i);
+            let point = geometry::closest_point_on_segment(&circle.center, &start, &end);
+            let distance = point.sub(&circle.center).mag();
+            if distance < closest_distance {
+                closest_distance = distance;
+                closest_point = point;
+            }
+        }
+
+        if closest_distance >= circle.radius {
+            return None;
+        }
+
+        Some(closest_point.sub(&circle.center))
+    }
+
+    //Contact between the heightfield and a convex point set (polygon or line)
+    fn convex_contact(&self, points: &[Vec2D]) -> Option<Vec2D> {
+        let bounds = Aabb::from_points(points);
+        let (first, last) = match self.column_range(bounds.min.x, bounds.max.x) {
+            Some(range) => range,
+            None => return None
+        };
+
+        let mut deepest: Option<(usize, Real)> = None;
+        for i in first..(last + 1) {
+            //Solid part of the column reaching below the shape
+            let (start, end) = self.column(i);
+            let bottom = bounds.min.y.min(start.y).min(end.y) - 1.0;
+            let column = [start, Vec2D::new(start.x, bottom), Vec2D::new(end.x, bottom), end];
+
+            if let Some((_, depth)) = geometry::sat_contact(points, &column) {
+                let is_deeper = match deepest {
+                    Some((_, deepest_depth)) => depth > deepest_depth,
+                    None => true
+                };
+                if is_deeper {
+                    deepest = Some((i, depth));
+                }
+            }
         }
+
+        //Only the surface normal is used so that shapes slide over the walls between columns
+        deepest.map(|(i, _)| self.column_normal(i))
     }
 }
 
@@ -71,66 +575,104 @@ impl Group {
             velocity: Vec2D::new(0.0, 0.0),
             friction: 0.0,
             is_static: false,
+            category: 1,
+            charge: 0.0,
+            out_of_bounds: OutOfBounds::Despawn,
+            bounds: Aabb::empty(),
         }
     }
 
     pub fn add_object(&mut self, object: impl RenderableObject + 'static) {
         self.mass += object.get_mass();
+        self.bounds = self.bounds.union(&object.bounds());
         self.objects.push(Box::new(object));
 
-        //Recalculate COM
+        //Recalculate COM... massless groups use the average position of their members
         let mut com = Vec2D::new(0.0, 0.0);
         for object in self.objects.iter() {
-            com.add(&object.get_com().mult(object.get_mass()));
+            if self.mass > 0.0 {
+                com = com.add(&object.get_com().mult(object.get_mass()));
+            } else {
+                com = com.add(&object.get_com());
+            }
+        }
+        if self.mass > 0.0 {
+            com = com.mult(1.0/self.mass);
+        } else {
+            com = com.mult(1.0/self.objects.len() as Real);
         }
-        com = com.mult(1.0/self.mass);
 
         self.com = com;
     }
 
-    pub fn create_polygon(points: Vec<Vec2D>, mass: f64) -> Group {
+    //Must be called after members in objects are modified directly
+    pub fn update_bounds(&mut self) {
+        let mut bounds = Aabb::empty();
+        for object in self.objects.iter() {
+            bounds = bounds.union(&object.bounds());
+        }
+
+        self.bounds = bounds;
+    }
+
+    pub fn create_polygon(points: Vec<Vec2D>, mass: Real) -> Group {
         let mut result = Group::new();
         for i in 0..(points.len() - 1) {
-            let mut line = Line::new(points[i].clone(), points[i+1].clone());
+            let mut line = Line::new(points[i], points[i+1]);
             line.set_static(false);
-            line.set_mass(mass / (points.len() as f64));
+            line.set_mass(mass / (points.len() as Real));
             result.add_object(line);
         }
 
         return result;
     }
+
+    //Decomposes an arbitrary simple polygon into convex pieces so that it has a solid interior
+    //Mass is distributed over the pieces by area
+    pub fn create_concave_polygon(points: Vec<Vec2D>, mass: Real) -> Result<Group, PolygonError> {
+        let triangles = geometry::triangulate(&points)?;
+        let total_area: Real = triangles.iter().map(|t| geometry::signed_area(t)).sum();
+
+        let mut result = Group::new();
+        for triangle in triangles {
+            let piece_mass = mass * geometry::signed_area(&triangle) / total_area;
+            result.add_object(Polygon::new(triangle, piece_mass)?);
+        }
+
+        Ok(result)
+    }
 }
 
 impl Object for Circle{
     fn get_com(&self) -> Vec2D {
-        self.center.clone()
+        self.center
     }
 
     fn set_com(&mut self, com: &Vec2D) {
-        self.center = com.clone();
+        self.center = *com;
     }
 
-    fn get_mass(&self) -> f64 {
+    fn get_mass(&self) -> Real {
         self.mass
     }
 
-    fn set_mass(&mut self, mass: f64) {
+    fn set_mass(&mut self, mass: Real) {
         self.mass = mass;
     }
 
     fn get_velocity(&self) -> Vec2D {
-        self.velocity.clone()
+        self.velocity
     }
 
     fn set_velocity(&mut self, velocity: &Vec2D) {
-        self.velocity = velocity.clone();
+        self.velocity = *velocity;
     }
 
-    fn get_friction(&self) -> f64 {
+    fn get_friction(&self) -> Real {
         self.friction
     }
 
-    fn set_friction(&mut self, friction_k: f64) {
+    fn set_friction(&mut self, friction_k: Real) {
         self.friction = friction_k;
     }
 
@@ -145,6 +687,30 @@ impl Object for Circle{
     fn set_static(&mut self, is_static: bool) {
         self.is_static = is_static;
     }
+
+    fn get_category(&self) -> u32 {
+        self.category
+    }
+
+    fn set_category(&mut self, category: u32) {
+        self.category = category;
+    }
+
+    fn get_charge(&self) -> Real {
+        self.charge
+    }
+
+    fn set_charge(&mut self, charge: Real) {
+        self.charge = charge;
+    }
+
+    fn get_out_of_bounds(&self) -> OutOfBounds {
+        self.out_of_bounds
+    }
+
+    fn set_out_of_bounds(&mut self, policy: OutOfBounds) {
+        self.out_of_bounds = policy;
+    }
 }
 
 impl Object for Line {
@@ -159,27 +725,259 @@ impl Object for Line {
         self.end_point = self.end_point.add(&translation_vec);
     }
 
-    fn get_mass(&self) -> f64 {
+    fn get_mass(&self) -> Real {
+        self.mass
+    }
+
+    fn set_mass(&mut self, mass: Real) {
+        self.mass = mass;
+    }
+
+    fn get_velocity(&self) -> Vec2D {
+        self.velocity
+    }
+
+    fn set_velocity(&mut self, velocity: &Vec2D) {
+        self.velocity = *velocity;
+    }
+
+    fn get_friction(&self) -> Real {
+        self.friction
+    }
+
+    fn set_friction(&mut self, friction_k: Real) {
+        self.friction = friction_k;
+    }
+
+    fn get_static(&self) -> bool {
+        self.is_static
+    }
+
+    fn set_static(&mut self, is_static: bool) {
+        self.is_static = is_static;
+    }
+
+    fn get_category(&self) -> u32 {
+        self.category
+    }
+
+    fn set_category(&mut self, category: u32) {
+        self.category = category;
+    }
+
+    fn get_charge(&self) -> Real {
+        self.charge
+    }
+
+    fn set_charge(&mut self, charge: Real) {
+        self.charge = charge;
+    }
+
+    fn get_out_of_bounds(&self) -> OutOfBounds {
+        self.out_of_bounds
+    }
+
+    fn set_out_of_bounds(&mut self, policy: OutOfBounds) {
+        self.out_of_bounds = policy;
+    }
+
+    fn as_any(&self) -> &Any {
+        self
+    }
+}
+
+impl Object for Polygon {
+    fn get_com(&self) -> Vec2D {
+        geometry::centroid(&self.points)
+    }
+
+    fn set_com(&mut self, com: &Vec2D) {
+        let translation_vec = com.sub(&self.get_com());
+
+        for point in self.points.iter_mut() {
+            *point = point.add(&translation_vec);
+        }
+    }
+
+    fn get_mass(&self) -> Real {
+        self.mass
+    }
+
+    fn set_mass(&mut self, mass: Real) {
+        self.mass = mass;
+    }
+
+    fn get_velocity(&self) -> Vec2D {
+        self.velocity
+    }
+
+    fn set_velocity(&mut self, velocity: &Vec2D) {
+        self.velocity = *velocity;
+    }
+
+    fn get_friction(&self) -> Real {
+        self.friction
+    }
+
+    fn set_friction(&mut self, friction_k: Real) {
+        self.friction = friction_k;
+    }
+
+    fn get_static(&self) -> bool {
+        self.is_static
+    }
+
+    fn set_static(&mut self, is_static: bool) {
+        self.is_static = is_static;
+    }
+
+    fn get_category(&self) -> u32 {
+        self.category
+    }
+
+    fn set_category(&mut self, category: u32) {
+        self.category = category;
+    }
+
+    fn get_charge(&self) -> Real {
+        self.charge
+    }
+
+    fn set_charge(&mut self, charge: Real) {
+        self.charge = charge;
+    }
+
+    fn get_out_of_bounds(&self) -> OutOfBounds {
+        self.out_of_bounds
+    }
+
+    fn set_out_of_bounds(&mut self, policy: OutOfBounds) {
+        self.out_of_bounds = policy;
+    }
+
+    fn as_any(&self) -> &Any {
+        self
+    }
+}
+
+impl Object for Chain {
+    fn get_com(&self) -> Vec2D {
+        if self.points.is_empty() {
+            return Vec2D::new(0.0, 0.0);
+        }
+
+        let mut com = Vec2D::new(0.0, 0.0);
+        for point in self.points.iter() {
+            com = com.add(point);
+        }
+
+        com.mult(1.0 / self.points.len() as Real)
+    }
+
+    fn set_com(&mut self, com: &Vec2D) {
+        let translation_vec = com.sub(&self.get_com());
+
+        for point in self.points.iter_mut() {
+            *point = point.add(&translation_vec);
+        }
+        self.ghost_start = self.ghost_start.as_ref().map(|p| p.add(&translation_vec));
+        self.ghost_end = self.ghost_end.as_ref().map(|p| p.add(&translation_vec));
+        self.segment_tree.translate(&translation_vec);
+    }
+
+    fn get_mass(&self) -> Real {
+        self.mass
+    }
+
+    fn set_mass(&mut self, mass: Real) {
+        self.mass = mass;
+    }
+
+    fn get_velocity(&self) -> Vec2D {
+        self.velocity
+    }
+
+    fn set_velocity(&mut self, velocity: &Vec2D) {
+        self.velocity = *velocity;
+    }
+
+    fn get_friction(&self) -> Real {
+        self.friction
+    }
+
+    fn set_friction(&mut self, friction_k: Real) {
+        self.friction = friction_k;
+    }
+
+    fn get_static(&self) -> bool {
+        self.is_static
+    }
+
+    fn set_static(&mut self, is_static: bool) {
+        self.is_static = is_static;
+    }
+
+    fn get_category(&self) -> u32 {
+        self.category
+    }
+
+    fn set_category(&mut self, category: u32) {
+        self.category = category;
+    }
+
+    fn get_charge(&self) -> Real {
+        self.charge
+    }
+
+    fn set_charge(&mut self, charge: Real) {
+        self.charge = charge;
+    }
+
+    fn get_out_of_bounds(&self) -> OutOfBounds {
+        self.out_of_bounds
+    }
+
+    fn set_out_of_bounds(&mut self, policy: OutOfBounds) {
+        self.out_of_bounds = policy;
+    }
+
+    fn as_any(&self) -> &Any {
+        self
+    }
+}
+
+impl Object for Heightfield {
+    fn get_com(&self) -> Vec2D {
+        let width = self.column_count() as Real * self.spacing;
+        Vec2D::new(self.origin.x + width * 0.5, self.origin.y)
+    }
+
+    fn set_com(&mut self, com: &Vec2D) {
+        let translation_vec = com.sub(&self.get_com());
+        self.origin = self.origin.add(&translation_vec);
+    }
+
+    fn get_mass(&self) -> Real {
         self.mass
     }
 
-    fn set_mass(&mut self, mass: f64) {
+    fn set_mass(&mut self, mass: Real) {
         self.mass = mass;
     }
 
     fn get_velocity(&self) -> Vec2D {
-        self.velocity.clone()
+        self.velocity
     }
 
     fn set_velocity(&mut self, velocity: &Vec2D) {
-        self.velocity = velocity.clone();
+        self.velocity = *velocity;
     }
 
-    fn get_friction(&self) -> f64 {
+    fn get_friction(&self) -> Real {
         self.friction
     }
 
-    fn set_friction(&mut self, friction_k: f64) {
+    fn set_friction(&mut self, friction_k: Real) {
         self.friction = friction_k;
     }
 
@@ -191,6 +989,30 @@ impl Object for Line {
         self.is_static = is_static;
     }
 
+    fn get_category(&self) -> u32 {
+        self.category
+    }
+
+    fn set_category(&mut self, category: u32) {
+        self.category = category;
+    }
+
+    fn get_charge(&self) -> Real {
+        self.charge
+    }
+
+    fn set_charge(&mut self, charge: Real) {
+        self.charge = charge;
+    }
+
+    fn get_out_of_bounds(&self) -> OutOfBounds {
+        self.out_of_bounds
+    }
+
+    fn set_out_of_bounds(&mut self, policy: OutOfBounds) {
+        self.out_of_bounds = policy;
+    }
+
     fn as_any(&self) -> &Any {
         self
     }
@@ -198,7 +1020,7 @@ impl Object for Line {
 
 impl Object for Group {
     fn get_com(&self) -> Vec2D {
-        self.com.clone()
+        self.com
     }
 
     fn set_com(&mut self, com: &Vec2D) {
@@ -209,30 +1031,31 @@ impl Object for Group {
             object.set_com(&obj_current_pos.add(&displacement));
         }
 
-        self.com = com.clone();
+        self.bounds = self.bounds.translate(&displacement);
+        self.com = *com;
     }
 
-    fn get_mass(&self) -> f64 {
+    fn get_mass(&self) -> Real {
         self.mass
     }
 
-    fn set_mass(&mut self, mass: f64) {
+    fn set_mass(&mut self, mass: Real) {
         //Do nothing ... mass should only changes when adding new objects
     }
 
     fn get_velocity(&self) -> Vec2D {
-        self.velocity.clone()
+        self.velocity
     }
 
     fn set_velocity(&mut self, velocity: &Vec2D) {
-        self.velocity = velocity.clone();
+        self.velocity = *velocity;
     }
 
-    fn get_friction(&self) -> f64 {
+    fn get_friction(&self) -> Real {
         self.friction
     }
 
-    fn set_friction(&mut self, friction_k: f64) {
+    fn set_friction(&mut self, friction_k: Real) {
         self.friction = friction_k;
     }
 
@@ -244,6 +1067,30 @@ impl Object for Group {
         self.is_static = is_static;
     }
 
+    fn get_category(&self) -> u32 {
+        self.category
+    }
+
+    fn set_category(&mut self, category: u32) {
+        self.category = category;
+    }
+
+    fn get_charge(&self) -> Real {
+        self.charge
+    }
+
+    fn set_charge(&mut self, charge: Real) {
+        self.charge = charge;
+    }
+
+    fn get_out_of_bounds(&self) -> OutOfBounds {
+        self.out_of_bounds
+    }
+
+    fn set_out_of_bounds(&mut self, policy: OutOfBounds) {
+        self.out_of_bounds = policy;
+    }
+
     fn as_any(&self) -> &Any {
         self
     }
@@ -276,6 +1123,18 @@ impl Collidable for Circle {
             }
 
             return false
+        } else if other.as_any().is::<Polygon>() {
+            //Use collision detection already implemented for Polygons and Circles
+            let polygon: &Polygon = other.as_any().downcast_ref::<Polygon>().unwrap();
+            return polygon.has_collided(self as &RenderableObject);
+        } else if other.as_any().is::<Chain>() {
+            //Use collision detection already implemented for Chains and Circles
+            let chain: &Chain = other.as_any().downcast_ref::<Chain>().unwrap();
+            return chain.has_collided(self as &RenderableObject);
+        } else if other.as_any().is::<Heightfield>() {
+            //Use collision detection already implemented for Heightfields and Circles
+            let heightfield: &Heightfield = other.as_any().downcast_ref::<Heightfield>().unwrap();
+            return heightfield.has_collided(self as &RenderableObject);
         } else if other.as_any().is::<Group>() {
             //Use collision detection already implemented for Groups and Circles
             let group: &Group = other.as_any().downcast_ref::<Group>().unwrap();
@@ -301,6 +1160,18 @@ impl Collidable for Circle {
                 line.end_point.sub(&line.start_point)
                     .reject_on(&line.end_point.sub(&self.center))
             );
+        } else if other.as_any().is::<Polygon>() {
+            //Use collision detection already implemented for Polygons and Circles
+            let polygon: &Polygon = other.as_any().downcast_ref::<Polygon>().unwrap();
+            return polygon.collision_direction(self as &RenderableObject);
+        } else if other.as_any().is::<Chain>() {
+            //Use collision detection already implemented for Chains and Circles
+            let chain: &Chain = other.as_any().downcast_ref::<Chain>().unwrap();
+            return chain.collision_direction(self as &RenderableObject);
+        } else if other.as_any().is::<Heightfield>() {
+            //Use collision detection already implemented for Heightfields and Circles
+            let heightfield: &Heightfield = other.as_any().downcast_ref::<Heightfield>().unwrap();
+            return heightfield.collision_direction(self as &RenderableObject);
         } else if other.as_any().is::<Group>() {
             //Use collision detection already implemented for Groups and Circles
             let group: &Group = other.as_any().downcast_ref::<Group>().unwrap();
@@ -309,6 +1180,11 @@ impl Collidable for Circle {
 
         return None;
     }
+
+    fn bounds(&self) -> Aabb {
+        let radius = Vec2D::new(self.radius, self.radius);
+        Aabb::new(self.center.sub(&radius), self.center.add(&radius))
+    }
 }
 
 impl Collidable for Line {
@@ -326,14 +1202,26 @@ impl Collidable for Line {
             let line1_displacement = self.end_point.sub(&self.start_point);
             let line2_displacement = line2.end_point.sub(&line2.start_point);
 
-            //Parallel lines cannot intersect
-            if line1_displacement.y / line1_displacement.x == line2_displacement.y / line2_displacement.x {
+            //Parallel lines cannot intersect (comparing cross product avoids dividing by zero for vertical lines)
+            if line1_displacement.cross(&line2_displacement) == 0.0 {
                 return false;
             }
 
             let t1_solved = (line2_displacement.x*(line2.start_point.y - self.start_point.y) - line2_displacement.y*(line2.start_point.x - self.start_point.x)) / (line2_displacement.x * line1_displacement.y - line1_displacement.x * line2_displacement.y);
             let t2_solved = (line1_displacement.x*(line2.start_point.y - self.start_point.y) - line1_displacement.y*(line2.start_point.x - self.start_point.x)) / (line2_displacement.x * line1_displacement.y - line1_displacement.x * line2_displacement.y);
             return  t1_solved < 1.0 && t1_solved > 0.0 && t2_solved < 1.0 && t2_solved > 0.0;
+        } else if other.as_any().is::<Polygon>() {
+            //Use collision detection already implemented for Polygons and Lines
+            let polygon: &Polygon = other.as_any().downcast_ref::<Polygon>().unwrap();
+            return polygon.has_collided(self as &RenderableObject);
+        } else if other.as_any().is::<Chain>() {
+            //Use collision detection already implemented for Chains and Lines
+            let chain: &Chain = other.as_any().downcast_ref::<Chain>().unwrap();
+            return chain.has_collided(self as &RenderableObject);
+        } else if other.as_any().is::<Heightfield>() {
+            //Use collision detection already implemented for Heightfields and Lines
+            let heightfield: &Heightfield = other.as_any().downcast_ref::<Heightfield>().unwrap();
+            return heightfield.has_collided(self as &RenderableObject);
         } else if other.as_any().is::<Group>() {
             //Use collision detection already implemented for Groups and Lines
             let group: &Group = other.as_any().downcast_ref::<Group>().unwrap();
@@ -348,6 +1236,18 @@ impl Collidable for Line {
             //Use collision detection already implemented for Circles and Lines
             let circle: &Circle = other.as_any().downcast_ref::<Circle>().unwrap();
             return circle.collision_direction(self as &RenderableObject);
+        } else if other.as_any().is::<Polygon>() {
+            //Use collision detection already implemented for Polygons and Lines
+            let polygon: &Polygon = other.as_any().downcast_ref::<Polygon>().unwrap();
+            return polygon.collision_direction(self as &RenderableObject);
+        } else if other.as_any().is::<Chain>() {
+            //Use collision detection already implemented for Chains and Lines
+            let chain: &Chain = other.as_any().downcast_ref::<Chain>().unwrap();
+            return chain.collision_direction(self as &RenderableObject);
+        } else if other.as_any().is::<Heightfield>() {
+            //Use collision detection already implemented for Heightfields and Lines
+            let heightfield: &Heightfield = other.as_any().downcast_ref::<Heightfield>().unwrap();
+            return heightfield.collision_direction(self as &RenderableObject);
         } else if other.as_any().is::<Group>() {
             //Use collision detection already implemented for Groups and Lines
             let group: &Group = other.as_any().downcast_ref::<Group>().unwrap();
@@ -363,8 +1263,8 @@ impl Collidable for Line {
         let line1_displacement = self.end_point.sub(&self.start_point);
         let line2_displacement = line2.end_point.sub(&line2.start_point);
 
-        //Parallel lines cannot intersect
-        if line1_displacement.y / line1_displacement.x == line2_displacement.y / line2_displacement.x {
+        //Parallel lines cannot intersect (comparing cross product avoids dividing by zero for vertical lines)
+        if line1_displacement.cross(&line2_displacement) == 0.0 {
             return None;
         }
 
@@ -386,6 +1286,136 @@ impl Collidable for Line {
 
         return None;
     }
+
+    fn bounds(&self) -> Aabb {
+        Aabb::from_points(&[self.start_point, self.end_point])
+    }
+}
+
+impl Collidable for Polygon {
+    fn has_collided(&self, other: &RenderableObject) -> bool {
+        self.collision_direction(other).is_some()
+    }
+
+    fn collision_direction(&self, other: &RenderableObject) -> Option<Vec2D> {
+        if other.as_any().is::<Circle>() {
+            let circle: &Circle = other.as_any().downcast_ref::<Circle>().unwrap();
+            return self.circle_contact(circle);
+        } else if other.as_any().is::<Line>() {
+            //Treat the line as a two point convex shape
+            let line: &Line = other.as_any().downcast_ref::<Line>().unwrap();
+            return geometry::sat_axis(&self.points, &[line.start_point, line.end_point]);
+        } else if other.as_any().is::<Polygon>() {
+            let polygon: &Polygon = other.as_any().downcast_ref::<Polygon>().unwrap();
+            return geometry::sat_axis(&self.points, &polygon.points);
+        } else if other.as_any().is::<Chain>() {
+            //Use collision detection already implemented for Chains and Polygons
+            let chain: &Chain = other.as_any().downcast_ref::<Chain>().unwrap();
+            return chain.collision_direction(self as &RenderableObject);
+        } else if other.as_any().is::<Heightfield>() {
+            //Use collision detection already implemented for Heightfields and Polygons
+            let heightfield: &Heightfield = other.as_any().downcast_ref::<Heightfield>().unwrap();
+            return heightfield.collision_direction(self as &RenderableObject);
+        } else if other.as_any().is::<Group>() {
+            //Use collision detection already implemented for Groups and Polygons
+            let group: &Group = other.as_any().downcast_ref::<Group>().unwrap();
+            return group.collision_direction(self as &RenderableObject);
+        }
+
+        return None;
+    }
+
+    fn bounds(&self) -> Aabb {
+        Aabb::from_points(&self.points)
+    }
+}
+
+impl Collidable for Chain {
+    fn has_collided(&self, other: &RenderableObject) -> bool {
+        self.collision_direction(other).is_some()
+    }
+
+    fn collision_direction(&self, other: &RenderableObject) -> Option<Vec2D> {
+        if other.as_any().is::<Circle>() {
+            let circle: &Circle = other.as_any().downcast_ref::<Circle>().unwrap();
+            return self.circle_contact(circle);
+        } else if other.as_any().is::<Line>() {
+            let line: &Line = other.as_any().downcast_ref::<Line>().unwrap();
+            return self.convex_contact(&[line.start_point, line.end_point]);
+        } else if other.as_any().is::<Polygon>() {
+            let polygon: &Polygon = other.as_any().downcast_ref::<Polygon>().unwrap();
+            return self.convex_contact(&polygon.points);
+        } else if other.as_any().is::<Chain>() {
+            let chain: &Chain = other.as_any().downcast_ref::<Chain>().unwrap();
+            for i in 0..self.segment_count() {
+                let (start, end) = self.segment(i);
+                for j in chain.query(&Aabb::from_points(&[start, end])) {
+                    let (other_start, other_end) = chain.segment(j);
+                    if geometry::segments_intersect(&start, &end, &other_start, &other_end) {
+                        return Some(end.sub(&start).perp());
+                    }
+                }
+            }
+        } else if other.as_any().is::<Group>() {
+            //Use collision detection already implemented for Groups and Chains
+            let group: &Group = other.as_any().downcast_ref::<Group>().unwrap();
+            return group.collision_direction(self as &RenderableObject);
+        }
+
+        return None;
+    }
+
+    fn bounds(&self) -> Aabb {
+        match self.segment_tree.bounds() {
+            Some(bounds) => bounds,
+            None => Aabb::from_points(&self.points)
+        }
+    }
+}
+
+impl Collidable for Heightfield {
+    fn has_collided(&self, other: &RenderableObject) -> bool {
+        self.collision_direction(other).is_some()
+    }
+
+    fn collision_direction(&self, other: &RenderableObject) -> Option<Vec2D> {
+        if other.as_any().is::<Circle>() {
+            let circle: &Circle = other.as_any().downcast_ref::<Circle>().unwrap();
+            return self.circle_contact(circle);
+        } else if other.as_any().is::<Line>() {
+            let line: &Line = other.as_any().downcast_ref::<Line>().unwrap();
+            return self.convex_contact(&[line.start_point, line.end_point]);
+        } else if other.as_any().is::<Polygon>() {
+            let polygon: &Polygon = other.as_any().downcast_ref::<Polygon>().unwrap();
+            return self.convex_contact(&polygon.points);
+        } else if other.as_any().is::<Group>() {
+            //Use collision detection already implemented for Groups and Heightfields
+            let group: &Group = other.as_any().downcast_ref::<Group>().unwrap();
+            return group.collision_direction(self as &RenderableObject);
+        }
+
+        return None;
+    }
+
+    fn bounds(&self) -> Aabb {
+        //Everything below the surface is solid but the box stops depth below the lowest height so it stays finite
+        let mut min_height = 0.0;
+        let mut max_height = 0.0;
+        for (i, height) in self.heights.iter().enumerate() {
+            if i == 0 || *height < min_height {
+                min_height = *height;
+            }
+            if i == 0 || *height > max_height {
+                max_height = *height;
+            }
+        }
+        let width = self.column_count() as Real * self.spacing;
+
+        Aabb::new(
+            Vec2D::new(self.origin.x, self.origin.y + min_height - self.depth.max(0.0)),
+            Vec2D::new(self.origin.x + width, self.origin.y + max_height)
+        )
+    }
 }
 
 impl Collidable for Group {
@@ -428,4 +1458,47 @@ impl Collidable for Group {
 
         return None;
     }
-}
\ No newline at end of file
+
+    fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_heightfields_are_rejected() {
+        let origin = Vec2D::new(0.0, 0.0);
+        assert_eq!(Heightfield::new(origin, 0.0, vec![0.0, 1.0]).err(), Some(HeightfieldError::InvalidSpacing));
+        assert_eq!(Heightfield::new(origin, Real::NAN, vec![0.0, 1.0]).err(), Some(HeightfieldError::InvalidSpacing));
+        assert_eq!(Heightfield::new(origin, 1.0, vec![0.0]).err(), Some(HeightfieldError::TooFewHeights));
+        assert_eq!(Heightfield::new(origin, 1.0, vec![0.0, 1.0]).unwrap().column_count(), 1);
+    }
+
+    //Direction a circle resting just past the end of a flat segment is pushed along
+    fn push_past_end(ghost_end: Option<Vec2D>) -> Vec2D {
+        let mut chain = Chain::new(vec![Vec2D::new(0.0, 0.0), Vec2D::new(1.0, 0.0)]);
+        chain.ghost_end = ghost_end;
+        let circle = Circle::new(1.0, Vec2D::new(1.05, 0.3), 0.5);
+        chain.collision_direction(&circle).unwrap()
+    }
+
+    #[test]
+    fn ghost_vertices_smooth_seams() {
+        //Without a neighbour the end is an exposed corner and pushes away from the vertex
+        assert!(push_past_end(None).x.abs() > 0.01);
+        //Continuing flat or bending toward the circle hides the corner so only the segment normal is used
+        assert!(push_past_end(Some(Vec2D::new(2.0, 0.0))).x.abs() < 1e-9);
+        assert!(push_past_end(Some(Vec2D::new(2.0, 1.0))).x.abs() < 1e-9);
+        //Bending away from the circle keeps the corner exposed
+        assert!(push_past_end(Some(Vec2D::new(2.0, -1.0))).x.abs() > 0.01);
+
+        let mut chain = Chain::new(vec![Vec2D::new(0.0, 0.0), Vec2D::new(1.0, 0.0)]);
+        let circle = Circle::new(1.0, Vec2D::new(-0.05, 0.3), 0.5);
+        assert!(chain.collision_direction(&circle).unwrap().x.abs() > 0.01);
+        chain.ghost_start = Some(Vec2D::new(-1.0, 0.0));
+        assert!(chain.collision_direction(&circle).unwrap().x.abs() < 1e-9);
+    }
+}
@@ -0,0 +1,228 @@
+use physics::Vec2D;
+use physics::Real;
+use physics::geometry;
+use physics::contact;
+use physics::bounds::Aabb;
+
+use renderer::RenderableObject;
+
+#[derive(Debug, Clone)]
+pub struct PointMass {
+    pub position: Vec2D,
+    pub velocity: Vec2D,
+    //Points with zero mass are pinned in place
+    pub mass: Real
+}
+
+//Damped spring connecting points a and b
+#[derive(Debug, Clone)]
+pub struct Spring {
+    pub a: usize,
+    pub b: usize,
+    pub rest_length: Real,
+    pub stiffness: Real,
+    pub damping: Real
+}
+
+//Deformable body made of point masses connected by damped springs
+#[derive(Debug, Clone)]
+pub struct SoftBody {
+    pub points: Vec<PointMass>,
+    pub springs: Vec<Spring>,
+    //Indices of the points forming the outline in counter-clockwise order
+    pub outline: Vec<usize>,
+    //Pressure times area stays constant like an ideal gas (pressure = gas_pressure / area)... zero disables pressure
+    pub gas_pressure: Real,
+    //External acceleration applied to every point (ex. gravity)
+    pub acceleration: Vec2D,
+    //Radius of the points when colliding with bodies in the world
+    pub point_radius: Real,
+    pub restitution: Real,
+    //Stiff springs need several smaller steps per world timestep to stay stable
+    pub substeps: usize,
+    pub color: [f32; 4]
+}
+
+impl PointMass {
+    pub fn new(position: Vec2D, mass: Real) -> PointMass {
+        PointMass {position, velocity: Vec2D::new(0.0, 0.0), mass}
+    }
+}
+
+impl SoftBody {
+    pub fn new() -> SoftBody {
+        SoftBody {
+            points: Vec::new(),
+            springs: Vec::new(),
+            outline: Vec::new(),
+            gas_pressure: 0.0,
+            acceleration: Vec2D::new(0.0, 0.0),
+            point_radius: 0.1,
+            restitution: 0.0,
+            substeps: 8,
+            color: [0.0, 0.0, 0.0, 1.0]
+        }
+    }
+
+    //Returns the index of the new point
+    pub fn add_point(&mut self, position: Vec2D, mass: Real) -> usize {
+        self.points.push(PointMass::new(position, mass));
+        self.points.len() - 1
+    }
+
+    //Rest length is the current distance between the points
+    pub fn add_spring(&mut self, a: usize, b: usize, stiffness: Real, damping: Real) {
+        let rest_length = self.points[a].position.sub(&self.points[b].position).mag();
+        self.springs.push(Spring {a, b, rest_length, stiffness, damping});
+    }
+
+    //Ring of points held in shape by gas pressure
+    //pressure is the starting internal pressure... the ring inflates until the springs stretch by about pressure * radius / stiffness
+    pub fn create_blob(center: Vec2D, radius: Real, segments: usize, mass: Real, stiffness: Real, damping: Real, pressure: Real) -> SoftBody {
+        let mut body = SoftBody::new();
+        for i in 0..segments {
+            let angle = i as Real * 2.0 * ::std::f64::consts::PI as Real / segments as Real;
+            let position = center.add(&Vec2D::new(angle.cos(), angle.sin()).mult(radius));
+            body.add_point(position, mass / segments as Real);
+        }
+        for i in 0..segments {
+            body.add_spring(i, (i + 1) % segments, stiffness, damping);
+        }
+        body.outline = (0..segments).collect();
+        body.gas_pressure = pressure * body.area();
+
+        body
+    }
+
+    //Block of points on a grid held together by structural and shear springs
+    pub fn create_block(min: Vec2D, columns: usize, rows: usize, spacing: Real, mass: Real, stiffness: Real, damping: Real) -> SoftBody {
+        let mut body = SoftBody::new();
+        let point_mass = mass / ((columns + 1) * (rows + 1)) as Real;
+        for row in 0..(rows + 1) {
+            for col in 0..(columns + 1) {
+                body.add_point(min.add(&Vec2D::new(col as Real * spacing, row as Real * spacing)), point_mass);
+            }
+        }
+
+        let index = |col: usize, row: usize| row * (columns + 1) + col;
+        for row in 0..(rows + 1) {
+            for col in 0..(columns + 1) {
+                if col < columns {
+                    body.add_spring(index(col, row), index(col + 1, row), stiffness, damping);
+                }
+                if row < rows {
+                    body.add_spring(index(col, row), index(col, row + 1), stiffness, damping);
+                }
+                if col < columns && row < rows {
+                    body.add_spring(index(col, row), index(col + 1, row + 1), stiffness, damping);
+                    body.add_spring(index(col + 1, row), index(col, row + 1), stiffness, damping);
+                }
+            }
+        }
+
+        //Walk the border counter-clockwise starting from the bottom left corner
+        let mut outline = Vec::new();
+        for col in 0..columns {
+            outline.push(index(col, 0));
+        }
+        for row in 0..rows {
+            outline.push(index(columns, row));
+        }
+        for col in (1..(columns + 1)).rev() {
+            outline.push(index(col, rows));
+        }
+        for row in (1..(rows + 1)).rev() {
+            outline.push(index(0, row));
+        }
+        body.outline = outline;
+
+        body
+    }
+
+    pub fn outline_points(&self) -> Vec<Vec2D> {
+        self.outline.iter().map(|&i| self.points[i].position).collect()
+    }
+
+    //Signed area enclosed by the outline
+    pub fn area(&self) -> Real {
+        geometry::signed_area(&self.outline_points())
+    }
+
+    pub fn get_mass(&self) -> Real {
+        self.points.iter().map(|p| p.mass).sum()
+    }
+
+    pub fn get_com(&self) -> Vec2D {
+        let mass = self.get_mass();
+        let mut com = Vec2D::new(0.0, 0.0);
+        for point in self.points.iter() {
+            com = com.add(&point.position.mult(point.mass));
+        }
+
+        if mass > 0.0 { com.mult(1.0 / mass) } else { com }
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        let extent = Vec2D::new(self.point_radius, self.point_radius);
+        let bounds = Aabb::from_points(&self.points.iter().map(|p| p.position).collect::<Vec<Vec2D>>());
+        Aabb::new(bounds.min.sub(&extent), bounds.max.add(&extent))
+    }
+
+    fn forces(&self) -> Vec<Vec2D> {
+        let mut forces = vec![Vec2D::new(0.0, 0.0); self.points.len()];
+
+        for spring in self.springs.iter() {
+            let a = &self.points[spring.a];
+            let b = &self.points[spring.b];
+            let delta = b.position.sub(&a.position);
+            let direction = match delta.try_unit() {
+                Some(direction) => direction,
+                None => continue
+            };
+
+            //Hooke's law plus damping of the relative velocity along the spring
+            let stretch = delta.mag() - spring.rest_length;
+            let relative_speed = b.velocity.sub(&a.velocity).dot(&direction);
+            let force = direction.mult(spring.stiffness * stretch + spring.damping * relative_speed);
+            forces[spring.a] = forces[spring.a].add(&force);
+            forces[spring.b] = forces[spring.b].sub(&force);
+        }
+
+        //Ideal gas pressure pushes every outline edge outwards in proportion to its length
+        let area = self.area();
+        if self.gas_pressure != 0.0 && self.outline.len() >= 3 && area.abs() > geometry::EPSILON {
+            let pressure = self.gas_pressure / area;
+            for i in 0..self.outline.len() {
+                let a = self.outline[i];
+                let b = self.outline[(i + 1) % self.outline.len()];
+                let edge = self.points[b].position.sub(&self.points[a].position);
+                //Outward normal for counter-clockwise outlines (sign flips with the area for clockwise ones)
+                let force = Vec2D::new(edge.y, -edge.x).mult(pressure * 0.5);
+                forces[a] = forces[a].add(&force);
+                forces[b] = forces[b].add(&force);
+            }
+        }
+
+        forces
+    }
+
+    //Advances the body by timestep and collides its points against objects
+    pub fn step(&mut self, timestep: Real, objects: &mut [Box<RenderableObject>]) {
+        let substeps = self.substeps.max(1);
+        let dt = timestep / substeps as Real;
+
+        for _ in 0..substeps {
+            let forces = self.forces();
+            for (point, force) in self.points.iter_mut().zip(forces.iter()) {
+                if point.mass <= 0.0 {
+                    continue;
+                }
+
+                //Semi-implicit Euler
+                point.velocity = point.velocity.add(&force.mult(1.0 / point.mass).add(&self.acceleration).mult(dt));
+                point.position = point.position.add(&point.velocity.mult(dt));
+                contact::collide_point(&mut point.position, &mut point.velocity, point.mass, self.point_radius, self.restitution, objects);
+            }
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use physics::Vec2D;
+use physics::Real;
+use physics::geometry;
+use physics::shapes::Line;
+use physics::shapes::Chain;
+
+use renderer::RenderableObject;
+
+use std::io;
+use std::io::Write;
+
+//Every non-static body with mass counts as a gas particle
+fn is_particle(obj: &RenderableObject) -> bool {
+    !obj.get_static() && obj.get_mass() > 0.0
+}
+
+pub fn kinetic_energy(objects: &[Box<RenderableObject>]) -> Real {
+    objects.iter()
+        .filter(|obj| is_particle(&***obj))
+        .map(|obj| {
+            let velocity = obj.get_velocity();
+            0.5 * obj.get_mass() * velocity.dot(&velocity)
+        })
+        .sum()
+}
+
+//Two degrees of freedom per particle in 2D... KE = N k T
+pub fn temperature(objects: &[Box<RenderableObject>], boltzmann: Real) -> Real {
+    let count = objects.iter().filter(|obj| is_particle(&***obj)).count();
+    if count == 0 || boltzmann <= 0.0 {
+        return 0.0;
+    }
+
+    kinetic_energy(objects) / (count as Real * boltzmann)
+}
+
+//Static lines and chains act as container walls
+pub fn is_wall(obj: &RenderableObject) -> bool {
+    obj.get_static() && (obj.as_any().is::<Line>() || obj.as_any().is::<Chain>())
+}
+
+//Segments making up a wall as (start, end)
+fn wall_segments(obj: &RenderableObject) -> Vec<(Vec2D, Vec2D)> {
+    if obj.as_any().is::<Line>() {
+        let line = obj.as_any().downcast_ref::<Line>().unwrap();
+        vec![(line.start_point, line.end_point)]
+    } else if obj.as_any().is::<Chain>() {
+        let chain = obj.as_any().downcast_ref::<Chain>().unwrap();
+        (0..chain.segment_count()).map(|i| chain.segment(i)).collect()
+    } else {
+        Vec::new()
+    }
+}
+
+//Total length of the walls
+pub fn wall_length(objects: &[Box<RenderableObject>]) -> Real {
+    objects.iter()
+        .filter(|obj| is_wall(&***obj))
+        .flat_map(|obj| wall_segments(&**obj))
+        .map(|(start, end)| end.sub(&start).mag())
+        .sum()
+}
+
+//Momentum a body at position pushes into a wall when its velocity changes from before to after
+//The push is along the direction from the closest point of the wall to the body
+//Signed so a body that is turned back towards the wall while still touching it takes the push back
+pub fn wall_impulse(wall: &RenderableObject, position: &Vec2D, mass: Real, before: &Vec2D, after: &Vec2D) -> Real {
+    let mut closest: Option<(Vec2D, Real)> = None;
+    for (start, end) in wall_segments(wall) {
+        let t = geometry::closest_parameter_on_segment(position, &start, &end);
+        let point = start.add(&end.sub(&start).mult(t));
+        let distance = position.sub(&point).mag();
+        let is_closer = match closest {
+            Some((_, closest_distance)) => distance < closest_distance,
+            None => true
+        };
+        if is_closer {
+            closest = Some((point, distance));
+        }
+    }
+
+    match closest {
+        Some((point, _)) => mass * after.sub(before).dot(&position.sub(&point).normalize_or_zero()),
+        None => 0.0
+    }
+}
+
+//2D Maxwell-Boltzmann probability density of speed for particles of mass at temperature
+pub fn maxwell_boltzmann(speed: Real, mass: Real, temperature: Real, boltzmann: Real) -> Real {
+    let thermal = boltzmann * temperature;
+    if thermal <= 0.0 || speed < 0.0 {
+        return 0.0;
+    }
+
+    mass * speed / thermal * (-mass * speed * speed / (2.0 * thermal)).exp()
+}
+
+//Observables of one step
+#[derive(Debug, Clone)]
+pub struct ThermoSample {
+    pub step: usize,
+    pub kinetic_energy: Real,
+    pub temperature: Real,
+    //Force per unit length on the walls
+    pub pressure: Real,
+    //Probability density of speeds... bin i covers speeds from i * bin_width to (i + 1) * bin_width
+    pub histogram: Vec<Real>,
+    //Maxwell-Boltzmann density at the middle of every bin for the measured temperature
+    pub maxwell_boltzmann: Vec<Real>,
+    pub bin_width: Real
+}
+
+impl ThermoSample {
+    //Total variation distance between the measured and expected speed distributions... 0.0 is a perfect match
+    pub fn deviation(&self) -> Real {
+        self.histogram.iter().zip(self.maxwell_boltzmann.iter())
+            .map(|(observed, expected)| (observed - expected).abs() * self.bin_width)
+            .sum::<Real>() * 0.5
+    }
+}
+
+//Records a sample of the gas every step
+//The world reports momentum handed to the walls (static lines and chains) through record_wall_impulse
+pub struct ThermoAnalysis {
+    //Boltzmann constant (1.0 in reduced units)
+    pub boltzmann: Real,
+    pub bins: usize,
+    //Speeds above this land in the last bin
+    pub max_speed: Real,
+    pub samples: Vec<ThermoSample>,
+    wall_impulse: Real,
+    step: usize
+}
+
+impl ThermoAnalysis {
+    pub fn new(bins: usize, max_speed: Real) -> ThermoAnalysis {
+        ThermoAnalysis {
+            boltzmann: 1.0,
+            bins,
+            max_speed,
+            samples: Vec::new(),
+            wall_impulse: 0.0,
+            step: 0
+        }
+    }
+
+    pub fn record_wall_impulse(&mut self, impulse: Real) {
+        self.wall_impulse += impulse;
+    }
+
+    //Measures the gas and the wall impulse collected since the last sample
+    pub fn sample(&mut self, timestep: Real, objects: &[Box<RenderableObject>]) -> &ThermoSample {
+        let particles: Vec<(Real, Vec2D)> = objects.iter()
+            .filter(|obj| is_particle(&***obj))
+            .map(|obj| (obj.get_mass(), obj.get_velocity()))
+            .collect();
+
+        let kinetic_energy = kinetic_energy(objects);
+        let temperature = temperature(objects, self.boltzmann);
+
+        let length = wall_length(objects);
+        let pressure = if length > 0.0 && timestep > 0.0 { self.wall_impulse / (timestep * length) } else { 0.0 };
+        self.wall_impulse = 0.0;
+
+        let bins = self.bins.max(1);
+        let bin_width = self.max_speed / bins as Real;
+        let mut histogram = vec![0.0; bins];
+        for &(_, velocity) in particles.iter() {
+            let bin = ((velocity.mag() / bin_width) as usize).min(bins - 1);
+            histogram[bin] += 1.0;
+        }
+        let count = particles.len() as Real;
+        for density in histogram.iter_mut() {
+            *density /= count.max(1.0) * bin_width;
+        }
+
+        //Mixed masses give the average of every particle's distribution
+        let maxwell_boltzmann = (0..bins)
+            .map(|bin| {
+                let speed = (bin as Real + 0.5) * bin_width;
+                particles.iter()
+                    .map(|&(mass, _)| maxwell_boltzmann(speed, mass, temperature, self.boltzmann))
+                    .sum::<Real>() / count.max(1.0)
+            })
+            .collect();
+
+        self.samples.push(ThermoSample {
+            step: self.step,
+            kinetic_energy,
+            temperature,
+            pressure,
+            histogram,
+            maxwell_boltzmann,
+            bin_width
+        });
+        self.step += 1;
+
+        self.samples.last().unwrap()
+    }
+
+    //Writes one CSV row per step and speed bin
+    pub fn write_csv<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "step,kinetic_energy,temperature,pressure,speed,observed,maxwell_boltzmann")?;
+        for sample in self.samples.iter() {
+            for (bin, (observed, expected)) in sample.histogram.iter().zip(sample.maxwell_boltzmann.iter()).enumerate() {
+                let speed = (bin as Real + 0.5) * sample.bin_width;
+                writeln!(writer, "{},{},{},{},{},{},{}", sample.step, sample.kinetic_energy, sample.temperature, sample.pressure, speed, observed, expected)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use physics::World;
+    use physics::shapes::Circle;
+
+    //Gas bouncing around inside a square chain like the one in main.rs
+    fn arena() -> World {
+        let mut world = World::new(0.0, 0.01);
+        world.add_object(Chain::new_loop(vec![
+            Vec2D::new(-5.0, 5.0),
+            Vec2D::new(5.0, 5.0),
+            Vec2D::new(5.0, -5.0),
+            Vec2D::new(-5.0, -5.0)
+        ]));
+        for i in 0..20 {
+            let mut particle = Circle::new(1.0, Vec2D::new((i % 5) as Real * 1.5 - 3.0, (i / 5) as Real * 1.5 - 2.0), 0.2);
+            let angle = i as Real * 2.4;
+            particle.velocity = Vec2D::new(angle.cos(), angle.sin()).mult(5.0);
+            world.add_object(particle);
+        }
+        world.thermo = Some(ThermoAnalysis::new(10, 10.0));
+        world
+    }
+
+    #[test]
+    fn chain_walls_count_towards_length() {
+        let mut world = arena();
+        world.add_object(Line::new(Vec2D::new(-5.0, 0.0), Vec2D::new(-2.0, 4.0)));
+        assert_eq!(wall_length(&world.objects), 45.0);
+    }
+
+    #[test]
+    fn gas_in_a_chain_arena_has_pressure() {
+        let mut world = arena();
+        for _ in 0..500 {
+            world.update();
+        }
+
+        let samples = &world.thermo.as_ref().unwrap().samples;
+        let pressure = samples.iter().map(|sample| sample.pressure).sum::<Real>() / samples.len() as Real;
+        //Ideal gas in 2D... P A = N k T = KE for the 10 x 10 arena
+        let ideal = kinetic_energy(&world.objects) / 100.0;
+        assert!((pressure / ideal - 1.0).abs() < 0.5);
+    }
+}
@@ -0,0 +1,286 @@
+use physics::Vec2D;
+use physics::Real;
+use physics::World;
+use physics::Object;
+use physics::shapes::Polygon;
+use physics::shapes::Chain;
+
+use std::error::Error;
+use std::fmt;
+
+//Kind of static geometry generated from the solid tiles
+pub enum TileCollider {
+    //Solid tiles are greedily merged into as few rectangles as possible
+    Rectangles,
+    //Outlines of solid regions become closed chains so no seams are left between tiles
+    Chains
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TilemapError {
+    //Number of tiles given doesn't match width * height
+    TileCountMismatch {expected: usize, found: usize}
+}
+
+impl fmt::Display for TilemapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TilemapError::TileCountMismatch {expected, found} => write!(f, "tile count {} does not match the grid size {}", found, expected)
+        }
+    }
+}
+
+impl Error for TilemapError {}
+
+//Grid of solid/empty tiles
+//Tile (col, row) is stored at tiles[row * width + col] where row 0 is the top row
+pub struct Tilemap {
+    pub width: usize,
+    pub height: usize,
+    pub tiles: Vec<bool>,
+    //World position of the top left corner of the grid
+    pub origin: Vec2D,
+    pub tile_size: Real
+}
+
+impl Tilemap {
+    pub fn new(width: usize, height: usize, tiles: Vec<bool>) -> Result<Tilemap, TilemapError> {
+        if tiles.len() != width * height {
+            return Err(TilemapError::TileCountMismatch {expected: width * height, found: tiles.len()});
+        }
+
+        Ok(Tilemap {
+            width,
+            height,
+            tiles,
+            origin: Vec2D::new(0.0, 0.0),
+            tile_size: 1.0
+        })
+    }
+
+    //Any non zero tile is treated as solid
+    pub fn from_u8(width: usize, height: usize, tiles: &[u8]) -> Result<Tilemap, TilemapError> {
+        Tilemap::new(width, height, tiles.iter().map(|&tile| tile != 0).collect())
+    }
+
+    pub fn is_solid(&self, col: i64, row: i64) -> bool {
+        if col < 0 || row < 0 || col >= self.width as i64 || row >= self.height as i64 {
+            return false;
+        }
+
+        self.tiles[row as usize * self.width + col as usize]
+    }
+
+    //World position of a grid corner
+    fn corner(&self, col: usize, row: usize) -> Vec2D {
+        Vec2D::new(self.origin.x + col as Real * self.tile_size, self.origin.y - row as Real * self.tile_size)
+    }
+
+    //Merges solid tiles into rectangles... each rectangle is grown right first and then down
+    pub fn rectangles(&self) -> Vec<Polygon> {
+        let mut covered = vec![false; self.tiles.len()];
+        let mut rectangles: Vec<Polygon> = Vec::new();
+
+        for row in 0..self.height {
+            for col in 0..self.width {
+                if !self.tiles[row * self.width + col] || covered[row * self.width + col] {
+                    continue;
+                }
+
+                let mut rect_width = 1;
+                while col + rect_width < self.width &&
+                    self.tiles[row * self.width + col + rect_width] &&
+                    !covered[row * self.width + col + rect_width] {
+                    rect_width += 1;
+                }
+
+                let mut rect_height = 1;
+                'grow: while row + rect_height < self.height {
+                    for c in col..(col + rect_width) {
+                        let index = (row + rect_height) * self.width + c;
+                        if !self.tiles[index] || covered[index] {
+                            break 'grow;
+                        }
+                    }
+                    rect_height += 1;
+                }
+
+                for r in row..(row + rect_height) {
+                    for c in col..(col + rect_width) {
+                        covered[r * self.width + c] = true;
+                    }
+                }
+
+                //A zero tile_size gives degenerate rectangles that can't collide with anything
+                let rectangle = Polygon::new(vec![
+                    self.corner(col, row + rect_height),
+                    self.corner(col + rect_width, row + rect_height),
+                    self.corner(col + rect_width, row),
+                    self.corner(col, row)
+                ], 1.0);
+                if let Ok(mut rectangle) = rectangle {
+                    rectangle.set_static(true);
+                    rectangles.push(rectangle);
+                }
+            }
+        }
+
+        rectangles
+    }
+
+    //Traces the outline of every solid region into closed chains
+    //Outer outlines run counter-clockwise and holes run clockwise so solid tiles are always on the left
+    pub fn chains(&self) -> Vec<Chain> {
+        //Boundary edges between solid and empty tiles as (start corner, end corner) in grid coordinates
+        let mut edges: Vec<((usize, usize), (usize, usize))> = Vec::new();
+        for row in 0..self.height {
+            for col in 0..self.width {
+                if !self.tiles[row * self.width + col] {
+                    continue;
+                }
+                let (c, r) = (col as i64, row as i64);
+
+                if !self.is_solid(c, r + 1) {
+                    edges.push(((col, row + 1), (col + 1, row + 1)));
+                }
+                if !self.is_solid(c + 1, r) {
+                    edges.push(((col + 1, row + 1), (col + 1, row)));
+                }
+                if !self.is_solid(c, r - 1) {
+                    edges.push(((col + 1, row), (col, row)));
+                }
+                if !self.is_solid(c - 1, r) {
+                    edges.push(((col, row), (col, row + 1)));
+                }
+            }
+        }
+
+        //Outgoing edges of every grid corner
+        let corner_index = |corner: (usize, usize)| corner.1 * (self.width + 1) + corner.0;
+        let mut outgoing: Vec<Vec<usize>> = vec![Vec::new(); (self.width + 1) * (self.height + 1)];
+        for (i, edge) in edges.iter().enumerate() {
+            outgoing[corner_index(edge.0)].push(i);
+        }
+
+        //Direction of an edge in world orientation (y pointing up)
+        let direction = |edge: &((usize, usize), (usize, usize))| {
+            ((edge.1).0 as i64 - (edge.0).0 as i64, (edge.0).1 as i64 - (edge.1).1 as i64)
+        };
+
+        let mut used = vec![false; edges.len()];
+        let mut chains: Vec<Chain> = Vec::new();
+        for first in 0..edges.len() {
+            if used[first] {
+                continue;
+            }
+
+            let mut outline: Vec<(usize, usize)> = Vec::new();
+            let mut current = first;
+            loop {
+                used[current] = true;
+                outline.push(edges[current].0);
+
+                //Where regions touch diagonally a corner has two outgoing edges
+                //Taking the sharpest left turn keeps the regions as separate outlines
+                let (dx, dy) = direction(&edges[current]);
+                let mut next: Option<(usize, i64)> = None;
+                for &candidate in outgoing[corner_index(edges[current].1)].iter() {
+                    if used[candidate] && candidate != first {
+                        continue;
+                    }
+                    let (cx, cy) = direction(&edges[candidate]);
+                    let turn = dx * cy - dy * cx;
+                    let is_better = match next {
+                        Some((_, best_turn)) => turn > best_turn,
+                        None => true
+                    };
+                    if is_better {
+                        next = Some((candidate, turn));
+                    }
+                }
+
+                match next {
+                    Some((candidate, _)) if candidate != first => current = candidate,
+                    _ => break
+                }
+            }
+
+            //Drop corners in the middle of straight runs so each side is a single segment
+            let mut points: Vec<Vec2D> = Vec::new();
+            for i in 0..outline.len() {
+                let prev = outline[(i + outline.len() - 1) % outline.len()];
+                let corner = outline[i];
+                let next = outline[(i + 1) % outline.len()];
+                let incoming = (corner.0 as i64 - prev.0 as i64, corner.1 as i64 - prev.1 as i64);
+                let outgoing = (next.0 as i64 - corner.0 as i64, next.1 as i64 - corner.1 as i64);
+                if incoming.0 * outgoing.1 - incoming.1 * outgoing.0 != 0 {
+                    points.push(self.corner(corner.0, corner.1));
+                }
+            }
+
+            chains.push(Chain::new_loop(points));
+        }
+
+        chains
+    }
+
+    pub fn add_to_world(&self, world: &mut World, collider: TileCollider) {
+        match collider {
+            TileCollider::Rectangles => {
+                for rectangle in self.rectangles() {
+                    world.add_object(rectangle);
+                }
+            },
+            TileCollider::Chains => {
+                for chain in self.chains() {
+                    world.add_object(chain);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use physics::geometry;
+
+    #[test]
+    fn solid_block_merges_into_one_rectangle() {
+        let mut tilemap = Tilemap::new(3, 2, vec![true; 6]).unwrap();
+        tilemap.origin = Vec2D::new(-1.0, 4.0);
+        tilemap.tile_size = 2.0;
+
+        let rectangles = tilemap.rectangles();
+        assert_eq!(rectangles.len(), 1);
+        assert_eq!(rectangles[0].points.len(), 4);
+        assert_eq!(geometry::signed_area(&rectangles[0].points), 24.0);
+        assert!(rectangles[0].get_static());
+    }
+
+    #[test]
+    fn solid_block_outline_is_a_closed_chain() {
+        let tilemap = Tilemap::new(3, 2, vec![true; 6]).unwrap();
+
+        let chains = tilemap.chains();
+        assert_eq!(chains.len(), 1);
+        assert!(chains[0].is_loop);
+        //Straight runs are merged so only the four corners remain
+        assert_eq!(chains[0].points.len(), 4);
+        assert_eq!(chains[0].segment_count(), 4);
+        assert_eq!(geometry::signed_area(&chains[0].points), 6.0);
+    }
+
+    #[test]
+    fn holes_are_outlined_clockwise() {
+        let tilemap = Tilemap::from_u8(3, 3, &[
+            1, 1, 1,
+            1, 0, 1,
+            1, 1, 1
+        ]).unwrap();
+
+        let mut areas: Vec<Real> = tilemap.chains().iter().map(|chain| geometry::signed_area(&chain.points)).collect();
+        areas.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(areas, vec![-1.0, 9.0]);
+    }
+}
@@ -0,0 +1,91 @@
+use physics::Vec2D;
+use physics::Real;
+
+use std::ops::Mul;
+
+//Rotation stored as the cosine and sine of the angle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rot2 {
+    pub cos: Real,
+    pub sin: Real
+}
+
+//Rotation followed by a translation... maps local coordinates of a shape to world coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform2D {
+    pub rotation: Rot2,
+    pub translation: Vec2D
+}
+
+impl Rot2 {
+    //Counter-clockwise rotation by angle(radians)
+    pub fn new(angle: Real) -> Rot2 {
+        let (sin, cos) = angle.sin_cos();
+        Rot2 {cos, sin}
+    }
+
+    pub fn identity() -> Rot2 {
+        Rot2 {cos: 1.0, sin: 0.0}
+    }
+
+    pub fn angle(&self) -> Real {
+        self.sin.atan2(self.cos)
+    }
+
+    pub fn rotate(&self, v: &Vec2D) -> Vec2D {
+        Vec2D::new(self.cos * v.x - self.sin * v.y, self.sin * v.x + self.cos * v.y)
+    }
+
+    pub fn inverse(&self) -> Rot2 {
+        Rot2 {cos: self.cos, sin: -self.sin}
+    }
+}
+
+//Applies other first and then self
+impl Mul for Rot2 {
+    type Output = Rot2;
+
+    fn mul(self, other: Rot2) -> Rot2 {
+        Rot2 {
+            cos: self.cos * other.cos - self.sin * other.sin,
+            sin: self.sin * other.cos + self.cos * other.sin
+        }
+    }
+}
+
+impl Transform2D {
+    pub fn new(angle: Real, translation: Vec2D) -> Transform2D {
+        Transform2D {rotation: Rot2::new(angle), translation}
+    }
+
+    pub fn identity() -> Transform2D {
+        Transform2D {rotation: Rot2::identity(), translation: Vec2D::new(0.0, 0.0)}
+    }
+
+    //Maps a point from local to world coordinates
+    pub fn apply(&self, point: &Vec2D) -> Vec2D {
+        self.rotation.rotate(point) + self.translation
+    }
+
+    //Maps a direction from local to world coordinates (translation does not apply)
+    pub fn apply_vector(&self, v: &Vec2D) -> Vec2D {
+        self.rotation.rotate(v)
+    }
+
+    pub fn inverse(&self) -> Transform2D {
+        let rotation = self.rotation.inverse();
+        Transform2D {rotation, translation: -rotation.rotate(&self.translation)}
+    }
+}
+
+//Applies other first and then self
+impl Mul for Transform2D {
+    type Output = Transform2D;
+
+    fn mul(self, other: Transform2D) -> Transform2D {
+        Transform2D {
+            rotation: self.rotation * other.rotation,
+            translation: self.apply(&other.translation)
+        }
+    }
+}
@@ -0,0 +1,337 @@
+use physics::Vec2D;
+use physics::Real;
+use physics::contact;
+use physics::bounds::Aabb;
+
+use renderer::RenderableObject;
+
+#[derive(Debug, Clone)]
+pub struct Particle {
+    pub position: Vec2D,
+    //Position at the start of the last step... velocity is implied by position - previous
+    pub previous: Vec2D,
+    //Particles with zero mass are pinned in place
+    pub mass: Real
+}
+
+//Keeps particles a and b rest_length apart
+#[derive(Debug, Clone)]
+pub struct DistanceConstraint {
+    pub a: usize,
+    pub b: usize,
+    pub rest_length: Real,
+    //Inverse stiffness of the constraint (XPBD)... zero is perfectly rigid
+    pub compliance: Real
+}
+
+#[derive(Debug, Clone)]
+pub enum AnchorTarget {
+    //Fixed point in the world
+    World(Vec2D),
+    //Point at offset from the center of mass of World::objects[index]
+    Body(usize, Vec2D)
+}
+
+#[derive(Debug, Clone)]
+pub struct Anchor {
+    pub particle: usize,
+    pub target: AnchorTarget
+}
+
+//Ropes, cloth and nets made of Verlet particles held together by distance constraints
+//Constraints are solved iteratively with extended position based dynamics (XPBD)
+#[derive(Debug, Clone)]
+pub struct VerletBody {
+    pub particles: Vec<Particle>,
+    pub constraints: Vec<DistanceConstraint>,
+    pub anchors: Vec<Anchor>,
+    //External acceleration applied to every particle (ex. gravity)
+    pub acceleration: Vec2D,
+    //Constraints stretched past rest_length * tear_ratio break... None never tears
+    pub tear_ratio: Option<Real>,
+    //Fraction of the velocity kept every substep
+    pub damping: Real,
+    //Radius of the particles when colliding with bodies in the world
+    pub particle_radius: Real,
+    pub substeps: usize,
+    //Constraint passes per substep
+    pub iterations: usize,
+    pub color: [f32; 4]
+}
+
+impl Particle {
+    pub fn new(position: Vec2D, mass: Real) -> Particle {
+        Particle {position, previous: position, mass}
+    }
+
+    fn inverse_mass(&self) -> Real {
+        if self.mass > 0.0 { 1.0 / self.mass } else { 0.0 }
+    }
+}
+
+impl VerletBody {
+    pub fn new() -> VerletBody {
+        VerletBody {
+            particles: Vec::new(),
+            constraints: Vec::new(),
+            anchors: Vec::new(),
+            acceleration: Vec2D::new(0.0, 0.0),
+            tear_ratio: None,
+            damping: 0.999,
+            particle_radius: 0.05,
+            substeps: 4,
+            iterations: 4,
+            color: [0.0, 0.0, 0.0, 1.0]
+        }
+    }
+
+    //Returns the index of the new particle
+    pub fn add_particle(&mut self, position: Vec2D, mass: Real) -> usize {
+        self.particles.push(Particle::new(position, mass));
+        self.particles.len() - 1
+    }
+
+    //Rest length is the current distance between the particles
+    pub fn add_constraint(&mut self, a: usize, b: usize, compliance: Real) {
+        let rest_length = self.particles[a].position.sub(&self.particles[b].position).mag();
+        self.constraints.push(DistanceConstraint {a, b, rest_length, compliance});
+    }
+
+    pub fn anchor_to_world(&mut self, particle: usize, point: Vec2D) {
+        self.anchors.push(Anchor {particle, target: AnchorTarget::World(point)});
+    }
+
+    //Attaches a particle to World::objects[index] at its current position relative to the body
+    pub fn anchor_to_body(&mut self, particle: usize, index: usize, body: &RenderableObject) {
+        let offset = self.particles[particle].position.sub(&body.get_com());
+        self.anchors.push(Anchor {particle, target: AnchorTarget::Body(index, offset)});
+    }
+
+    //Chain of particles from start to end... zero segments is treated as one
+    pub fn create_rope(start: Vec2D, end: Vec2D, segments: usize, mass: Real, compliance: Real) -> VerletBody {
+        let segments = segments.max(1);
+        let mut rope = VerletBody::new();
+        for i in 0..(segments + 1) {
+            rope.add_particle(start.lerp(&end, i as Real / segments as Real), mass / (segments + 1) as Real);
+        }
+        for i in 0..segments {
+            rope.add_constraint(i, i + 1, compliance);
+        }
+
+        rope
+    }
+
+    //Grid of particles starting at top_left and hanging down... particle (col, row) is at index row * (columns + 1) + col
+    //Diagonal constraints resist shearing like woven cloth... without them the grid behaves like a net
+    pub fn create_cloth(top_left: Vec2D, columns: usize, rows: usize, spacing: Real, mass: Real, compliance: Real, shear: bool) -> VerletBody {
+        let mut cloth = VerletBody::new();
+        let particle_mass = mass / ((columns + 1) * (rows + 1)) as Real;
+        for row in 0..(rows + 1) {
+            for col in 0..(columns + 1) {
+                cloth.add_particle(top_left.add(&Vec2D::new(col as Real * spacing, -(row as Real) * spacing)), particle_mass);
+            }
+        }
+
+        let index = |col: usize, row: usize| row * (columns + 1) + col;
+        for row in 0..(rows + 1) {
+            for col in 0..(columns + 1) {
+                if col < columns {
+                    cloth.add_constraint(index(col, row), index(col + 1, row), compliance);
+                }
+                if row < rows {
+                    cloth.add_constraint(index(col, row), index(col, row + 1), compliance);
+                }
+                if shear && col < columns && row < rows {
+                    cloth.add_constraint(index(col, row), index(col + 1, row + 1), compliance);
+                    cloth.add_constraint(index(col + 1, row), index(col, row + 1), compliance);
+                }
+            }
+        }
+
+        cloth
+    }
+
+    pub fn get_mass(&self) -> Real {
+        self.particles.iter().map(|p| p.mass).sum()
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        let extent = Vec2D::new(self.particle_radius, self.particle_radius);
+        let bounds = Aabb::from_points(&self.particles.iter().map(|p| p.position).collect::<Vec<Vec2D>>());
+        Aabb::new(bounds.min.sub(&extent), bounds.max.add(&extent))
+    }
+
+    //Velocity of a particle over the last step
+    pub fn velocity(&self, particle: usize, timestep: Real) -> Vec2D {
+        let particle = &self.particles[particle];
+        particle.position.sub(&particle.previous).mult(1.0 / (timestep / self.substeps.max(1) as Real))
+    }
+
+    fn solve_constraints(&mut self, lambdas: &mut [Real], dt: Real) {
+        for (constraint, lambda) in self.constraints.iter().zip(lambdas.iter_mut()) {
+            let w_a = self.particles[constraint.a].inverse_mass();
+            let w_b = self.particles[constraint.b].inverse_mass();
+            let alpha = constraint.compliance / (dt * dt);
+            if w_a + w_b + alpha <= 0.0 {
+                continue;
+            }
+
+            let delta = self.particles[constraint.a].position.sub(&self.particles[constraint.b].position);
+            let normal = match delta.try_unit() {
+                Some(normal) => normal,
+                None => continue
+            };
+
+            let error = delta.mag() - constraint.rest_length;
+            let delta_lambda = (-error - alpha * *lambda) / (w_a + w_b + alpha);
+            *lambda += delta_lambda;
+
+            let a = &mut self.particles[constraint.a];
+            a.position = a.position.add(&normal.mult(w_a * delta_lambda));
+            let b = &mut self.particles[constraint.b];
+            b.position = b.position.sub(&normal.mult(w_b * delta_lambda));
+        }
+    }
+
+    //Pulls anchored particles onto their targets... anchored bodies are pulled back in proportion to their mass
+    //Bodies aren't moved here... their corrections are summed into displacements (one per object) and applied once by step
+    fn solve_anchors(&mut self, objects: &[Box<RenderableObject>], displacements: &mut [Vec2D]) {
+        for anchor in self.anchors.iter() {
+            let particle = &mut self.particles[anchor.particle];
+            match anchor.target {
+                AnchorTarget::World(point) => particle.position = point,
+                AnchorTarget::Body(index, offset) => {
+                    let body = match objects.get(index) {
+                        Some(body) => body,
+                        None => continue
+                    };
+
+                    let w_particle = particle.inverse_mass();
+                    let w_body = if body.get_static() || body.get_mass() <= 0.0 { 0.0 } else { 1.0 / body.get_mass() };
+                    let com = body.get_com().add(&displacements[index]);
+                    let error = particle.position.sub(&com.add(&offset));
+                    if w_particle + w_body <= 0.0 {
+                        continue;
+                    }
+
+                    particle.position = particle.position.sub(&error.mult(w_particle / (w_particle + w_body)));
+                    displacements[index] = displacements[index].add(&error.mult(w_body / (w_particle + w_body)));
+                }
+            }
+        }
+    }
+
+    //Removes constraints stretched past the tear ratio
+    fn tear(&mut self) {
+        let ratio = match self.tear_ratio {
+            Some(ratio) => ratio,
+            None => return
+        };
+
+        let particles = &self.particles;
+        self.constraints.retain(|constraint| {
+            let length = particles[constraint.a].position.sub(&particles[constraint.b].position).mag();
+            length <= constraint.rest_length * ratio
+        });
+    }
+
+    //Advances the body by timestep and collides its particles against objects
+    pub fn step(&mut self, timestep: Real, objects: &mut [Box<RenderableObject>]) {
+        let substeps = self.substeps.max(1);
+        let dt = timestep / substeps as Real;
+        let mut displacements = vec![Vec2D::new(0.0, 0.0); objects.len()];
+
+        //Particles pinned to a body sit inside it and would be pushed out of it every substep
+        let mut attached = vec![false; self.particles.len()];
+        for anchor in self.anchors.iter() {
+            if let AnchorTarget::Body(_, _) = anchor.target {
+                attached[anchor.particle] = true;
+            }
+        }
+
+        for _ in 0..substeps {
+            //Verlet integration
+            for particle in self.particles.iter_mut() {
+                if particle.mass <= 0.0 {
+                    particle.previous = particle.position;
+                    continue;
+                }
+
+                let velocity = particle.position.sub(&particle.previous).mult(self.damping);
+                particle.previous = particle.position;
+                particle.position = particle.position.add(&velocity).add(&self.acceleration.mult(dt * dt));
+            }
+
+            //Lagrange multipliers accumulate over the iterations of a substep
+            let mut lambdas = vec![0.0; self.constraints.len()];
+            for _ in 0..self.iterations.max(1) {
+                self.solve_constraints(&mut lambdas, dt);
+                self.solve_anchors(objects, &mut displacements);
+            }
+
+            for (particle, &attached) in self.particles.iter_mut().zip(attached.iter()) {
+                if particle.mass <= 0.0 || attached {
+                    continue;
+                }
+
+                //Collide with the implied velocity and store the response back into the previous position
+                let mut velocity = particle.position.sub(&particle.previous).mult(1.0 / dt);
+                contact::collide_point(&mut particle.position, &mut velocity, particle.mass, self.particle_radius, 0.0, objects);
+                particle.previous = particle.position.sub(&velocity.mult(dt));
+            }
+
+            self.tear();
+        }
+
+        //Rigid bodies are integrated by velocity so the net pull of the step becomes a velocity change
+        //The body covers the displacement over the next timestep instead of being moved twice
+        if timestep > 0.0 {
+            for (body, displacement) in objects.iter_mut().zip(displacements.iter()) {
+                if displacement.x != 0.0 || displacement.y != 0.0 {
+                    let velocity = body.get_velocity();
+                    body.set_velocity(&velocity.add(&displacement.mult(1.0 / timestep)));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use physics::World;
+    use physics::shapes::Circle;
+
+    //A ball hanging from a rope must not gain energy from the anchor
+    #[test]
+    fn anchored_body_does_not_gain_energy() {
+        let mut world = World::new(0.0, 0.01);
+        world.add_object(Circle::new(1.0, Vec2D::new(5.0, 5.0), 0.3));
+        let mut rope = VerletBody::create_rope(Vec2D::new(0.0, 5.0), Vec2D::new(5.0, 5.0), 10, 0.5, 0.0);
+        rope.acceleration = Vec2D::new(0.0, -9.8);
+        rope.anchor_to_world(0, Vec2D::new(0.0, 5.0));
+        rope.anchor_to_body(10, 0, &*world.objects[0]);
+        world.add_verlet_body(rope);
+
+        let pivot = Vec2D::new(0.0, 5.0);
+        for _ in 0..2000 {
+            let velocity = world.objects[0].get_velocity();
+            world.objects[0].set_velocity(&velocity.add(&Vec2D::new(0.0, -9.8 * world.timestep)));
+            world.update();
+
+            let com = world.objects[0].get_com();
+            let velocity = world.objects[0].get_velocity();
+            let energy = 0.5 * velocity.dot(&velocity) + 9.8 * (com.y - pivot.y);
+            assert!(energy < 0.5, "energy grew to {}", energy);
+            assert!(com.distance(&pivot) < 5.5, "rope stretched to {}", com.distance(&pivot));
+        }
+    }
+
+    #[test]
+    fn rope_without_segments_has_one() {
+        let rope = VerletBody::create_rope(Vec2D::new(0.0, 0.0), Vec2D::new(2.0, 0.0), 0, 1.0, 0.0);
+        assert_eq!(rope.particles.len(), 2);
+        assert_eq!(rope.constraints.len(), 1);
+        assert!(rope.particles.iter().all(|particle| particle.position.is_finite()));
+    }
+}
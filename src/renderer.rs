@@ -2,25 +2,36 @@ use piston_window::*;
 
 use physics::shapes::Circle;
 use physics::shapes::Line;
+use physics::shapes::Polygon;
+use physics::shapes::Chain;
+use physics::shapes::Heightfield;
 use physics::shapes::Group;
+use physics::softbody::SoftBody;
+use physics::verlet::VerletBody;
+use physics::particles::ParticleSystem;
+use physics::fluid::Fluid;
+use physics::buoyancy::WaterVolume;
 use physics::Vec2D;
+use physics::Real;
 use physics::World;
 use physics::Object;
 use physics::Collidable;
+use physics::bounds::Aabb;
 
 use piston_window::Line as GLine;
+use piston_window::Polygon as GPolygon;
 
 pub struct Camera {
-    pub x_min: f64,
-    pub x_max: f64,
-    pub y_min: f64,
-    pub y_max: f64,
-    pub screen_width: f64,
-    pub screen_height: f64
+    pub x_min: Real,
+    pub x_max: Real,
+    pub y_min: Real,
+    pub y_max: Real,
+    pub screen_width: Real,
+    pub screen_height: Real
 }
 
 impl Camera {
-    pub fn new(x_min: f64, x_max: f64, y_min: f64, y_max: f64, screen_width: f64, screen_height: f64) -> Camera {
+    pub fn new(x_min: Real, x_max: Real, y_min: Real, y_max: Real, screen_width: Real, screen_height: Real) -> Camera {
         Camera {
             x_min,
             x_max,
@@ -31,6 +42,23 @@ impl Camera {
         }
     }
 
+    //Region of the world that is visible
+    pub fn bounds(&self) -> Aabb {
+        Aabb::new(Vec2D::new(self.x_min, self.y_min), Vec2D::new(self.x_max, self.y_max))
+    }
+
+    //Camera that draws everything moved by displacement
+    pub fn shifted(&self, displacement: &Vec2D) -> Camera {
+        Camera::new(
+            self.x_min - displacement.x,
+            self.x_max - displacement.x,
+            self.y_min - displacement.y,
+            self.y_max - displacement.y,
+            self.screen_width,
+            self.screen_height
+        )
+    }
+
     pub fn cartesian(&self, screen_pos: &Vec2D) -> Vec2D {
         let x = (self.x_max - self.x_min) / self.screen_width * screen_pos.x + self.x_min;
         let y = (self.y_min - self.y_max) / self.screen_height * screen_pos.y + self.y_max;
@@ -50,7 +78,16 @@ pub trait Renderable {
     fn render(&self, context: &Context, graphics: &mut G2d, camera: &Camera);
 }
 
-pub trait RenderableObject: Renderable + Object + Collidable {}
+pub trait RenderableObject: Renderable + Object + Collidable {
+    //Deep copy of the body... Groups copy every member
+    fn box_clone(&self) -> Box<RenderableObject>;
+}
+
+impl Clone for Box<RenderableObject> {
+    fn clone(&self) -> Box<RenderableObject> {
+        self.box_clone()
+    }
+}
 
 impl Renderable for Circle {
     fn render(&self, context: &Context, graphics: &mut G2d, camera: &Camera) {
@@ -58,7 +95,7 @@ impl Renderable for Circle {
         let radius_x = camera.screen_width/(camera.x_max - camera.x_min) * self.radius;
         let radius_y = camera.screen_height/(camera.y_max - camera.y_min) * self.radius;
         graphics.ellipse(&Ellipse::new(self.color),
-                         [center.x - radius_x, center.y - radius_y, 2.0*radius_x, 2.0*radius_y],
+                         [(center.x - radius_x) as f64, (center.y - radius_y) as f64, (2.0*radius_x) as f64, (2.0*radius_y) as f64],
                             &context.draw_state, context.transform);
     }
 }
@@ -68,11 +105,55 @@ impl Renderable for Line {
         let start_point = camera.screen(&self.start_point);
         let end_point = camera.screen(&self.end_point);
         graphics.line(&GLine::new(self.color, 1.0),
-                        [start_point.x, start_point.y, end_point.x, end_point.y],
+                        [start_point.x as f64, start_point.y as f64, end_point.x as f64, end_point.y as f64],
                             &context.draw_state, context.transform);
     }
 }
 
+impl Renderable for Polygon {
+    fn render(&self, context: &Context, graphics: &mut G2d, camera: &Camera) {
+        let points: Vec<[f64; 2]> = self.points.iter()
+            .map(|p| {
+                let screen_point = camera.screen(p);
+                [screen_point.x as f64, screen_point.y as f64]
+            })
+            .collect();
+        graphics.polygon(&GPolygon::new(self.color), &points, &context.draw_state, context.transform);
+    }
+}
+
+impl Renderable for Chain {
+    fn render(&self, context: &Context, graphics: &mut G2d, camera: &Camera) {
+        for i in 0..self.segment_count() {
+            let (start, end) = self.segment(i);
+            let start_point = camera.screen(&start);
+            let end_point = camera.screen(&end);
+            graphics.line(&GLine::new(self.color, 1.0),
+                          [start_point.x as f64, start_point.y as f64, end_point.x as f64, end_point.y as f64],
+                          &context.draw_state, context.transform);
+        }
+    }
+}
+
+impl Renderable for Heightfield {
+    fn render(&self, context: &Context, graphics: &mut G2d, camera: &Camera) {
+        //Only draw the columns that are visible
+        let (first, last) = match self.column_range(camera.x_min, camera.x_max) {
+            Some(range) => range,
+            None => return
+        };
+
+        for i in first..(last + 1) {
+            let (start, end) = self.column(i);
+            let start_point = camera.screen(&start);
+            let end_point = camera.screen(&end);
+            graphics.line(&GLine::new(self.color, 1.0),
+                          [start_point.x as f64, start_point.y as f64, end_point.x as f64, end_point.y as f64],
+                          &context.draw_state, context.transform);
+        }
+    }
+}
+
 impl Renderable for Group {
     fn render(&self, context: &Context, graphics: &mut G2d, camera: &Camera) {
         for obj in self.objects.iter() {
@@ -81,14 +162,170 @@ impl Renderable for Group {
     }
 }
 
-impl RenderableObject for Circle {}
-impl RenderableObject for Line {}
-impl RenderableObject for Group {}
+impl Renderable for SoftBody {
+    fn render(&self, context: &Context, graphics: &mut G2d, camera: &Camera) {
+        let outline = self.outline_points();
+        for i in 0..outline.len() {
+            let start_point = camera.screen(&outline[i]);
+            let end_point = camera.screen(&outline[(i + 1) % outline.len()]);
+            graphics.line(&GLine::new(self.color, 1.0),
+                          [start_point.x as f64, start_point.y as f64, end_point.x as f64, end_point.y as f64],
+                          &context.draw_state, context.transform);
+        }
+    }
+}
+
+impl Renderable for VerletBody {
+    fn render(&self, context: &Context, graphics: &mut G2d, camera: &Camera) {
+        for constraint in self.constraints.iter() {
+            let start_point = camera.screen(&self.particles[constraint.a].position);
+            let end_point = camera.screen(&self.particles[constraint.b].position);
+            graphics.line(&GLine::new(self.color, 1.0),
+                          [start_point.x as f64, start_point.y as f64, end_point.x as f64, end_point.y as f64],
+                          &context.draw_state, context.transform);
+        }
+    }
+}
+
+//Draws every point as a square made of two triangles... all of them are sent in a single triangle list
+fn render_points(points: &[Vec2D], radius: Real, color: [f32; 4], context: &Context, graphics: &mut G2d, camera: &Camera) {
+    let half_width = (radius * camera.screen_width / (camera.x_max - camera.x_min)).max(0.5) as f64;
+    let half_height = (radius * camera.screen_height / (camera.y_max - camera.y_min)).max(0.5) as f64;
+    let transform = context.transform;
+    let mut vertices: Vec<[f32; 2]> = Vec::with_capacity(points.len() * 6);
+    for position in points.iter() {
+        let center = camera.screen(position);
+        let (x, y) = (center.x as f64, center.y as f64);
+        let corners = [
+            [x - half_width, y - half_height],
+            [x + half_width, y - half_height],
+            [x + half_width, y + half_height],
+            [x - half_width, y + half_height]
+        ];
+        for &corner in [0, 1, 2, 0, 2, 3].iter() {
+            let (cx, cy) = (corners[corner][0], corners[corner][1]);
+            vertices.push([triangulation::tx(transform, cx, cy), triangulation::ty(transform, cx, cy)]);
+        }
+    }
+
+    graphics.tri_list(&context.draw_state, &color, |f| f(&vertices));
+}
+
+impl Renderable for ParticleSystem {
+    fn render(&self, context: &Context, graphics: &mut G2d, camera: &Camera) {
+        render_points(&self.positions, self.radius, self.color, context, graphics, camera);
+    }
+}
+
+impl Renderable for Fluid {
+    fn render(&self, context: &Context, graphics: &mut G2d, camera: &Camera) {
+        render_points(&self.positions, self.particle_radius, self.color, context, graphics, camera);
+    }
+}
+
+impl Renderable for WaterVolume {
+    fn render(&self, context: &Context, graphics: &mut G2d, camera: &Camera) {
+        let points: Vec<[f64; 2]> = self.points.iter()
+            .map(|p| {
+                let screen_point = camera.screen(p);
+                [screen_point.x as f64, screen_point.y as f64]
+            })
+            .collect();
+        graphics.polygon(&GPolygon::new(self.color), &points, &context.draw_state, context.transform);
+    }
+}
+
+impl RenderableObject for Circle {
+    fn box_clone(&self) -> Box<RenderableObject> {
+        Box::new(self.clone())
+    }
+}
+
+impl RenderableObject for Line {
+    fn box_clone(&self) -> Box<RenderableObject> {
+        Box::new(self.clone())
+    }
+}
+
+impl RenderableObject for Polygon {
+    fn box_clone(&self) -> Box<RenderableObject> {
+        Box::new(self.clone())
+    }
+}
+
+impl RenderableObject for Chain {
+    fn box_clone(&self) -> Box<RenderableObject> {
+        Box::new(self.clone())
+    }
+}
+
+impl RenderableObject for Heightfield {
+    fn box_clone(&self) -> Box<RenderableObject> {
+        Box::new(self.clone())
+    }
+}
+
+impl RenderableObject for Group {
+    fn box_clone(&self) -> Box<RenderableObject> {
+        Box::new(self.clone())
+    }
+}
 
 impl Renderable for World {
     fn render(&self, context: &Context, graphics: &mut G2d, camera: &Camera) {
+        //Skip bodies that are off screen
+        let view = camera.bounds();
         for obj in self.objects.iter() {
-            obj.render(context, graphics, camera);
+            if obj.bounds().overlaps(&view) {
+                obj.render(context, graphics, camera);
+            }
+        }
+        //Bodies crossing the edge of a periodic world are also drawn on the opposite side
+        if let Some(periodic) = self.periodic {
+            let width = periodic.max.x - periodic.min.x;
+            let height = periodic.max.y - periodic.min.y;
+            for obj in self.objects.iter() {
+                let bounds = obj.bounds();
+                for &dx in [-width, 0.0, width].iter() {
+                    for &dy in [-height, 0.0, height].iter() {
+                        if dx == 0.0 && dy == 0.0 {
+                            continue;
+                        }
+
+                        let shift = Vec2D::new(dx, dy);
+                        let ghost = bounds.translate(&shift);
+                        if ghost.overlaps(&periodic) && ghost.overlaps(&view) {
+                            obj.render(context, graphics, &camera.shifted(&shift));
+                        }
+                    }
+                }
+            }
+        }
+        for body in self.soft_bodies.iter() {
+            if body.bounds().overlaps(&view) {
+                body.render(context, graphics, camera);
+            }
+        }
+        for body in self.verlet_bodies.iter() {
+            if body.bounds().overlaps(&view) {
+                body.render(context, graphics, camera);
+            }
+        }
+        for system in self.particle_systems.iter() {
+            if !system.is_empty() && system.bounds().overlaps(&view) {
+                system.render(context, graphics, camera);
+            }
+        }
+        for fluid in self.fluids.iter() {
+            if !fluid.is_empty() && fluid.bounds().overlaps(&view) {
+                fluid.render(context, graphics, camera);
+            }
+        }
+        //Drawn last so the translucent water tints the bodies inside it
+        for volume in self.water_volumes.iter() {
+            if volume.bounds().overlaps(&view) {
+                volume.render(context, graphics, camera);
+            }
         }
     }
 }
\ No newline at end of file